@@ -1,4 +1,4 @@
-use deep_learning_playground::neural_network::{self, activate_functions};
+use deep_learning_playground::neural_network::{self, activate_functions, one_hot};
 use deep_learning_playground::setup::dlfs::chap3;
 use deep_learning_playground::setup::mnist::{batched, load_data, test_dataset, Batched};
 use deep_learning_playground::utils::natural_transform::to_io;
@@ -27,12 +27,7 @@ fn compute(td: Batched, trained_data: &chap3::Chap3Param) -> io::Result<u32> {
         nn.next(&w.map(|x| *x as f64), &b.map(|x| *x as f64), &af);
     }
 
-    Ok(td
-        .labels
-        .iter()
-        .zip(nn.argmax().iter())
-        .map(|(l, r)| (*l as u32 == *r as u32) as u32)
-        .sum())
+    Ok(one_hot::count_correct(&td.labels, nn.neurons()) as u32)
 }
 
 fn execute(bsize: usize) -> io::Result<(f64, Duration)> {
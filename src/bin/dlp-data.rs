@@ -0,0 +1,420 @@
+extern crate deep_learning_playground;
+
+use deep_learning_playground::setup;
+use deep_learning_playground::setup::idx_dataset;
+use deep_learning_playground::setup::mnist;
+use deep_learning_playground::setup::stats::DatasetStats;
+use deep_learning_playground::setup::LoadOptions;
+use deep_learning_playground::utils::fetch_client::{
+    CacheRoot, ClientOptions, FetchClient, FileStatus, FileTransport, Manifest,
+};
+use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+
+const COMMANDS: [&str; 6] = ["datasets", "prefetch", "list", "verify", "purge", "stats"];
+
+const USAGE: &str = "\
+Usage: dlp-data [--cache-dir DIR] [--mirror DIR] [--quiet] <COMMAND> [DATASET...]
+
+Manage the datasets saved in the cache directory.
+
+Commands:
+    datasets    Print the names of the known datasets
+    prefetch    Download the datasets
+    list        List the saved files with their sizes and hash states
+    verify      Check the digests of the saved files. It fails if a file is missing or corrupted.
+    purge       Remove the saved files of the datasets which have been saved
+    stats       Print the class balance and pixel statistics of the image datasets
+
+DATASET is one of the known datasets or a path of a manifest file (.toml or .json).
+All the known datasets are used if it is omitted, except for stats which requires DATASET.
+
+Options:
+    --cache-dir DIR    Use DIR as the cache root instead of $DLP_CACHE_DIR or the user cache directory
+    --mirror DIR       Read the files from DIR instead of downloading them
+    --quiet            Do not report the progress of downloads
+    --allow-missing    Let verify succeed for the files which have not been downloaded
+    --split SPLIT      The split used by stats: train (default) or test
+    --bins N           The number of bins of the histogram printed by stats (default: 10)
+    --png DIR          Save the mean image of each class to DIR/DATASET by stats
+    -h, --help         Print this message
+
+The options taking a value also accept the form --OPTION=VALUE.";
+
+#[derive(Debug, PartialEq)]
+struct Args {
+    cache_dir: Option<PathBuf>,
+    mirror: Option<PathBuf>,
+    quiet: bool,
+    command: String,
+    datasets: Vec<String>,
+    allow_missing: bool,
+    train: bool,
+    bins: usize,
+    png: Option<PathBuf>,
+}
+
+impl Args {
+    fn options(&self) -> ClientOptions {
+        let mut opts = ClientOptions::new();
+        if self.quiet {
+            opts = opts.silent();
+        }
+        if let Some(ref dir) = self.cache_dir {
+            opts = opts.cache_root(CacheRoot::Dir(dir.clone()));
+        }
+        if let Some(ref dir) = self.mirror {
+            opts = opts.transport(Arc::new(FileTransport::mirror(dir.clone())));
+        }
+        opts
+    }
+}
+
+// It returns `None` if the help is requested.
+fn parse_args<I>(args: I) -> Result<Option<Args>, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut cache_dir = None;
+    let mut mirror = None;
+    let mut quiet = false;
+    let mut positional = Vec::new();
+    let mut train = true;
+    let mut bins = 10;
+    let mut png = None;
+    let mut allow_missing = false;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let (name, inline) = match arg.find('=') {
+            Some(i) if arg.starts_with("--") => (&arg[..i], Some(arg[i + 1..].to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || inline.clone().or_else(|| args.next());
+        match name {
+            "-h" | "--help" if inline.is_none() => return Ok(None),
+            "--quiet" if inline.is_none() => quiet = true,
+            "--allow-missing" if inline.is_none() => allow_missing = true,
+            "--cache-dir" => match value() {
+                Some(dir) => cache_dir = Some(PathBuf::from(dir)),
+                None => return Err("--cache-dir requires a directory".to_string()),
+            },
+            "--mirror" => match value() {
+                Some(dir) => mirror = Some(PathBuf::from(dir)),
+                None => return Err("--mirror requires a directory".to_string()),
+            },
+            "--split" => match value().as_deref() {
+                Some("train") => train = true,
+                Some("test") => train = false,
+                _ => return Err("--split requires train or test".to_string()),
+            },
+            "--bins" => match value().and_then(|n| n.parse().ok()) {
+                Some(n) if n > 0 => bins = n,
+                _ => return Err("--bins requires a positive number".to_string()),
+            },
+            "--png" => match value() {
+                Some(dir) => png = Some(PathBuf::from(dir)),
+                None => return Err("--png requires a directory".to_string()),
+            },
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+            _ => positional.push(arg),
+        }
+    }
+    if positional.is_empty() {
+        return Err("no command is specified".to_string());
+    }
+    let command = positional.remove(0);
+    if !COMMANDS.contains(&command.as_str()) {
+        return Err(format!("unknown command: {}", command));
+    }
+    Ok(Some(Args {
+        cache_dir,
+        mirror,
+        quiet,
+        command,
+        datasets: positional,
+        allow_missing,
+        train,
+        bins,
+        png,
+    }))
+}
+
+fn is_manifest(name: &str) -> bool {
+    matches!(
+        Path::new(name).extension().and_then(|ext| ext.to_str()),
+        Some("toml") | Some("json")
+    )
+}
+
+fn clients(args: &Args, opts: &ClientOptions) -> io::Result<Vec<(String, FetchClient<'static>)>> {
+    let names = if args.datasets.is_empty() {
+        setup::DATASET_NAMES.iter().map(|s| s.to_string()).collect()
+    } else {
+        args.datasets.clone()
+    };
+    names
+        .into_iter()
+        .map(|name| {
+            let client = if is_manifest(&name) {
+                FetchClient::from_manifest(&Manifest::from_path(&name)?, opts)?
+            } else {
+                setup::fetch_client(&name, opts)?
+            };
+            Ok((name, client))
+        })
+        .collect()
+}
+
+fn list(name: &str, client: &FetchClient) -> io::Result<bool> {
+    println!("{} ({})", name, client.dir_client.path().display());
+    let files = client.dir_client.list()?;
+    if files.is_empty() {
+        println!("    (not saved)");
+    }
+    let mut total = 0;
+    for file in files {
+        let status = match file.path.to_str() {
+            Some(fname) if client.dir_client.file.contains_key(fname) => {
+                client.status(fname)?.to_string()
+            }
+            _ => "-".to_string(),
+        };
+        println!(
+            "    {:>12}  {:<9}  {}",
+            file.size,
+            status,
+            file.path.display()
+        );
+        total += file.size;
+    }
+    println!("    {:>12}  total", total);
+    Ok(true)
+}
+
+fn verify(name: &str, client: &FetchClient, allow_missing: bool) -> io::Result<bool> {
+    let mut ok = true;
+    for (fname, status) in client.verify()? {
+        match status {
+            FileStatus::Corrupted(ref msg) => {
+                ok = false;
+                println!("{}: {}: {}", name, fname, msg);
+            }
+            FileStatus::Missing => {
+                ok &= allow_missing;
+                println!("{}: {}: {}", name, fname, status);
+            }
+            FileStatus::Verified => println!("{}: {}: {}", name, fname, status),
+        }
+    }
+    Ok(ok)
+}
+
+fn stats(args: &Args, opts: &ClientOptions) -> io::Result<bool> {
+    // Every dataset would be downloaded and decoded otherwise (e.g. EMNIST is over 500 MB).
+    if args.datasets.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "stats requires DATASET, e.g. dlp-data stats mnist",
+        ));
+    }
+    for name in args.datasets.iter() {
+        let dataset = idx_dataset::by_name(name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("stats supports only the datasets in MNIST layout: {}", name),
+            )
+        })?;
+        let key = if args.train {
+            mnist::train_dataset()
+        } else {
+            mnist::test_dataset()
+        };
+        let tensor = dataset.load_tensor_with(key, false, &LoadOptions::from(opts.clone()))?;
+        // The raw pixels are between 0 and 255, so the images are read only once.
+        let stats = DatasetStats::compute_with_range(
+            &tensor,
+            tensor.rows,
+            tensor.cols,
+            args.bins,
+            (0., 255.),
+        )?;
+        println!("{} ({})", name, if args.train { "train" } else { "test" });
+        print!("{}", stats);
+        if let Some(ref dir) = args.png {
+            for path in stats.save_mean_images(dir.join(name))? {
+                println!("saved {}", path.display());
+            }
+        }
+    }
+    Ok(true)
+}
+
+fn run(args: &Args) -> io::Result<bool> {
+    let opts = args.options();
+    match args.command.as_str() {
+        "datasets" => {
+            for name in setup::DATASET_NAMES {
+                println!("{}", name);
+            }
+            return Ok(true);
+        }
+        "stats" => return stats(args, &opts),
+        _ => (),
+    }
+
+    let mut ok = true;
+    for (name, client) in clients(args, &opts)? {
+        ok &= match args.command.as_str() {
+            "prefetch" => client.get().map(|_| true)?,
+            "list" => list(&name, &client)?,
+            "verify" => verify(&name, &client, args.allow_missing)?,
+            "purge" if !client.dir_client.exists() => {
+                println!("{} is not saved", name);
+                true
+            }
+            "purge" => {
+                client.dir_client.purge(client.lock_timeout())?;
+                println!("removed {}", client.dir_client.path().display());
+                true
+            }
+            _ => unreachable!(),
+        };
+    }
+    Ok(ok)
+}
+
+fn main() {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+    match run(&args) {
+        Ok(true) => (),
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use deep_learning_playground::utils::fetch_client::{HashAlgorithm, RemoteFileBuf};
+    use std::fs;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = parse(&[
+            "--cache-dir",
+            "/tmp/cache",
+            "--mirror=/tmp/mirror",
+            "--quiet",
+            "--allow-missing",
+            "--bins=20",
+            "--split",
+            "test",
+            "verify",
+            "mnist",
+            "kmnist",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(args.cache_dir, Some(PathBuf::from("/tmp/cache")));
+        assert_eq!(args.mirror, Some(PathBuf::from("/tmp/mirror")));
+        assert!(args.quiet && args.allow_missing && !args.train);
+        assert_eq!(args.bins, 20);
+        assert_eq!(args.command, "verify");
+        assert_eq!(args.datasets, vec!["mnist", "kmnist"]);
+        assert_eq!(
+            args.options().cache_root,
+            CacheRoot::Dir(PathBuf::from("/tmp/cache"))
+        );
+
+        // The value of the inline form may contain `=`.
+        let args = parse(&["--png=a=b", "stats", "mnist"]).unwrap().unwrap();
+        assert_eq!(args.png, Some(PathBuf::from("a=b")));
+        assert!(parse(&["--help", "list"]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        let cases: &[&[&str]] = &[
+            &[],
+            &["--quiet"],
+            &["download"],
+            &["--quiet=1", "list"],
+            &["--mirror"],
+            &["--bins=0", "stats"],
+            &["--split", "valid", "stats"],
+            &["--unknown", "list"],
+        ];
+        for args in cases {
+            assert!(parse(args).is_err(), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_verify_and_purge() {
+        const DATA: &[u8] = b"dlp-data fixture";
+        let root = tempfile::tempdir().unwrap();
+        let mirror = root.path().join("mirror");
+        fs::create_dir_all(&mirror).unwrap();
+        fs::write(mirror.join("data.bin"), DATA).unwrap();
+        let manifest = Manifest::new(
+            "fixture",
+            vec![RemoteFileBuf::new(
+                "https://example.com/",
+                "data.bin",
+                &HashAlgorithm::Sha256.hex_digest(DATA),
+                "",
+            )],
+        );
+        let manifest_path = root.path().join("fixture.json");
+        fs::write(&manifest_path, manifest.to_json_string().unwrap()).unwrap();
+        let cache = root.path().join("cache");
+        let dataset_dir = cache.join("fixture");
+
+        let run_with = |args: &[&str]| {
+            let mut all = vec![
+                "--quiet".to_string(),
+                format!("--cache-dir={}", cache.display()),
+                format!("--mirror={}", mirror.display()),
+            ];
+            all.extend(args.iter().map(|s| s.to_string()));
+            all.push(manifest_path.display().to_string());
+            run(&parse_args(all).unwrap().unwrap())
+        };
+
+        // The missing file fails unless it is allowed.
+        assert!(!run_with(&["verify"]).unwrap());
+        assert!(run_with(&["--allow-missing", "verify"]).unwrap());
+        // Purging the dataset which is not saved does nothing.
+        assert!(run_with(&["purge"]).unwrap());
+        assert!(!dataset_dir.exists());
+
+        assert!(run_with(&["prefetch"]).unwrap());
+        assert!(run_with(&["verify"]).unwrap());
+        fs::write(dataset_dir.join("data.bin"), b"corrupted").unwrap();
+        assert!(!run_with(&["--allow-missing", "verify"]).unwrap());
+
+        assert!(run_with(&["purge"]).unwrap());
+        assert!(!dataset_dir.join("data.bin").exists());
+        assert!(!run_with(&["verify"]).unwrap());
+    }
+}
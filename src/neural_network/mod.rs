@@ -5,6 +5,7 @@ use num::Float;
 use std::fmt;
 
 pub mod activate_functions;
+pub mod one_hot;
 
 #[derive(Default)]
 pub struct NeuralNetwork<T> {
@@ -88,6 +89,12 @@ impl<T: Float + 'static> NeuralNetwork<T> {
         self.neurons.dim()
     }
 
+    /// `neurons` returns the current matrix.
+    #[inline]
+    pub fn neurons(&self) -> &Array2<T> {
+        &self.neurons
+    }
+
     /// `argmax` returns the index of maximum value.
     /// 行毎の最大値
     #[inline]
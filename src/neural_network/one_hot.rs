@@ -0,0 +1,154 @@
+use failure::Error;
+use ndarray::Array2;
+use ndarray_stats::QuantileExt;
+use num::Float;
+
+/// `one_hot` encodes the labels into the one-hot matrix of shape (labels, classes)
+/// whose row \\(i\\) is 1 at the column `labels[i]` and 0 elsewhere.
+/// If a label is not less than `classes`, it returns `Err`.
+///
+/// # Arguments
+///
+/// * `labels` - The labels (e.g. `Batched::labels` of MNIST)
+/// * `classes` - The number of classes
+///
+/// # Examples
+///
+/// ```
+/// use deep_learning_playground::neural_network::one_hot::one_hot;
+/// use ndarray::arr2;
+///
+/// let t = one_hot::<f64, u8>(&[2, 0], 3).unwrap();
+/// assert_eq!(t, arr2(&[[0., 0., 1.], [1., 0., 0.]]));
+/// ```
+pub fn one_hot<T: Float, L: Copy + Into<usize>>(
+    labels: &[L],
+    classes: usize,
+) -> Result<Array2<T>, Error> {
+    smooth_one_hot(labels, classes, T::zero())
+}
+
+/// `smooth_one_hot` encodes the labels into the one-hot matrix with label smoothing
+/// \\[
+/// t_{ij}=(1-\varepsilon)\delta_{j,l_i}+\dfrac{\varepsilon}{K}
+/// \\]
+/// where \\(K\\) is the number of classes, so that each row still sums to 1.
+/// If a label is not less than `classes` or \\(\varepsilon\not\in[0,1]\\), it returns `Err`.
+///
+/// # Arguments
+///
+/// * `labels` - The labels
+/// * `classes` - The number of classes \\(K\\)
+/// * `smoothing` - The smoothing factor \\(\varepsilon\\). 0 is the same as `one_hot`.
+pub fn smooth_one_hot<T: Float, L: Copy + Into<usize>>(
+    labels: &[L],
+    classes: usize,
+    smoothing: T,
+) -> Result<Array2<T>, Error> {
+    if !(smoothing >= T::zero() && smoothing <= T::one()) {
+        return Err(failure::format_err!(
+            "the smoothing factor must be between 0 and 1"
+        ));
+    }
+    if let Some(&label) = labels.iter().find(|&&l| l.into() >= classes) {
+        return Err(failure::format_err!(
+            "the label {} is out of {} classes",
+            label.into(),
+            classes
+        ));
+    }
+    let off = smoothing / T::from(classes).unwrap();
+    let mut t = Array2::from_elem((labels.len(), classes), off);
+    for (i, &label) in labels.iter().enumerate() {
+        t[[i, label.into()]] = T::one() - smoothing + off;
+    }
+    Ok(t)
+}
+
+/// `decode` returns the label of each row of `t`, i.e. the index of the maximum value.
+/// It decodes both one-hot matrices and the outputs of the network (e.g. softmax).
+/// The label of the row is `None` if the row has NaN (e.g. the network has diverged)
+/// or `t` has no columns.
+pub fn decode<T: Float>(t: &Array2<T>) -> Vec<Option<usize>> {
+    t.outer_iter().map(|x| x.argmax().ok()).collect()
+}
+
+/// `count_correct` returns the number of rows of `t` whose label decoded by `decode`
+/// is equal to the corresponding element of `labels`.
+/// The rows which cannot be decoded are counted as incorrect.
+///
+/// # Arguments
+///
+/// * `labels` - The correct labels
+/// * `t` - The predictions of shape (labels, classes)
+pub fn count_correct<T: Float, L: Copy + Into<usize>>(labels: &[L], t: &Array2<T>) -> usize {
+    labels
+        .iter()
+        .zip(decode(t))
+        .filter(|&(&l, r)| r == Some(l.into()))
+        .count()
+}
+
+/// `accuracy` returns the ratio of the correct predictions between 0 and 1
+///
+/// # Arguments
+///
+/// * `labels` - The correct labels
+/// * `t` - The predictions of shape (labels, classes)
+pub fn accuracy<T: Float, L: Copy + Into<usize>>(labels: &[L], t: &Array2<T>) -> f64 {
+    if labels.is_empty() {
+        return 0.;
+    }
+    count_correct(labels, t) as f64 / labels.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::arr2;
+
+    #[test]
+    fn test_one_hot() {
+        let labels: Vec<u8> = vec![1, 0, 3];
+        let t = one_hot::<f32, _>(&labels, 4).unwrap();
+        assert_eq!(
+            t,
+            arr2(&[[0., 1., 0., 0.], [1., 0., 0., 0.], [0., 0., 0., 1.]])
+        );
+        assert_eq!(decode(&t), vec![Some(1), Some(0), Some(3)]);
+        assert!(one_hot::<f32, u8>(&[4], 4).is_err());
+
+        let t = smooth_one_hot::<f64, usize>(&[1, 0], 4, 0.2).unwrap();
+        let expected = arr2(&[[0.05, 0.85, 0.05, 0.05], [0.85, 0.05, 0.05, 0.05]]);
+        assert_eq!(t.dim(), expected.dim());
+        assert!(t
+            .iter()
+            .zip(expected.iter())
+            .all(|(x, y)| (x - y).abs() < 1e-12));
+        assert!(t.outer_iter().all(|r| (r.sum() - 1.).abs() < 1e-12));
+        assert_eq!(decode(&t), vec![Some(1), Some(0)]);
+        assert!(smooth_one_hot::<f64, u8>(&[0], 2, 1.5).is_err());
+    }
+
+    #[test]
+    fn test_accuracy() {
+        let t = arr2(&[[0.1, 0.9], [0.8, 0.2], [0.3, 0.7], [0.6, 0.4]]);
+        let labels: Vec<u8> = vec![1, 0, 0, 0];
+        assert_eq!(count_correct(&labels, &t), 3);
+        assert_eq!(accuracy(&labels, &t), 0.75);
+        assert_eq!(accuracy::<f64, u8>(&[], &Array2::zeros((0, 2))), 0.);
+    }
+
+    #[test]
+    fn test_undecodable_rows() {
+        let t = arr2(&[[0.1, 0.9], [f64::NAN, 0.2], [0.3, f64::NAN], [0.6, 0.4]]);
+        assert_eq!(decode(&t), vec![Some(1), None, None, Some(0)]);
+        let labels: Vec<u8> = vec![1, 0, 1, 0];
+        assert_eq!(count_correct(&labels, &t), 2);
+        assert_eq!(accuracy(&labels, &t), 0.5);
+
+        let empty = Array2::<f32>::zeros((2, 0));
+        assert_eq!(decode(&empty), vec![None, None]);
+        assert_eq!(count_correct::<f32, u8>(&[0, 0], &empty), 0);
+    }
+}
@@ -0,0 +1,549 @@
+use super::data_loader::Dataset;
+use super::mnist::MnistImage;
+use super::random::standard_normal;
+use ndarray::{s, Array2, Axis};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::io;
+use std::sync::Arc;
+use std::vec;
+
+/// `Transform` is a random transformation of a grayscale image of shape (rows, columns).
+/// The randomness comes only from `rng`, so the same seed gives the same result.
+pub trait Transform: Send + Sync {
+    /// `apply` returns the transformed image
+    fn apply(&self, image: &Array2<f64>, rng: &mut StdRng) -> Array2<f64>;
+}
+
+/// `Compose` applies the transforms in order
+pub struct Compose(pub vec::Vec<Box<dyn Transform>>);
+
+impl Transform for Compose {
+    fn apply(&self, image: &Array2<f64>, rng: &mut StdRng) -> Array2<f64> {
+        self.0
+            .iter()
+            .fold(image.clone(), |image, t| t.apply(&image, rng))
+    }
+}
+
+// The bilinear interpolation of `image` at (y, x). The outside of the image is 0.
+fn bilinear(image: &Array2<f64>, y: f64, x: f64) -> f64 {
+    let (rows, cols) = image.dim();
+    let (y0, x0) = (y.floor(), x.floor());
+    let (dy, dx) = (y - y0, x - x0);
+    let at = |r: f64, c: f64| -> f64 {
+        if r < 0. || c < 0. || r >= rows as f64 || c >= cols as f64 {
+            0.
+        } else {
+            image[[r as usize, c as usize]]
+        }
+    };
+    at(y0, x0) * (1. - dy) * (1. - dx)
+        + at(y0, x0 + 1.) * (1. - dy) * dx
+        + at(y0 + 1., x0) * dy * (1. - dx)
+        + at(y0 + 1., x0 + 1.) * dy * dx
+}
+
+// `warp` samples each pixel (y, x) of the output from `src(y, x)` of the input
+fn warp<F: Fn(f64, f64) -> (f64, f64)>(image: &Array2<f64>, src: F) -> Array2<f64> {
+    Array2::from_shape_fn(image.dim(), |(r, c)| {
+        let (y, x) = src(r as f64, c as f64);
+        bilinear(image, y, x)
+    })
+}
+
+// `affine` rotates by `angle` radians and scales by `scale` around the center of the image,
+// and then translates by (`ty`, `tx`) pixels.
+fn affine(image: &Array2<f64>, angle: f64, scale: f64, ty: f64, tx: f64) -> Array2<f64> {
+    let (rows, cols) = image.dim();
+    let (cy, cx) = ((rows as f64 - 1.) / 2., (cols as f64 - 1.) / 2.);
+    let (sin, cos) = angle.sin_cos();
+    warp(image, |y, x| {
+        // The inverse mapping from the output to the input
+        let (y, x) = (y - cy - ty, x - cx - tx);
+        (
+            (cos * y - sin * x) / scale + cy,
+            (sin * y + cos * x) / scale + cx,
+        )
+    })
+}
+
+// `check` returns the error of `InvalidInput` with `message` unless `valid`
+fn check(valid: bool, message: &str) -> io::Result<()> {
+    if valid {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidInput, message))
+    }
+}
+
+// A uniform sample between `lo` and `hi` (both inclusive). `lo` must not be greater than `hi`.
+fn uniform(rng: &mut StdRng, lo: f64, hi: f64) -> f64 {
+    rng.gen_range(lo, hi + f64::EPSILON.max(hi.abs() * f64::EPSILON))
+}
+
+/// `RandomTranslation` shifts the image by up to `max_dy` rows and `max_dx` columns
+#[derive(Debug, Clone, Copy)]
+pub struct RandomTranslation {
+    max_dy: f64,
+    max_dx: f64,
+}
+
+impl RandomTranslation {
+    /// `RandomTranslation` constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `max_dy` - The maximum shift in rows. It must be non-negative.
+    /// * `max_dx` - The maximum shift in columns. It must be non-negative.
+    pub fn new(max_dy: f64, max_dx: f64) -> io::Result<Self> {
+        check(
+            max_dy >= 0. && max_dx >= 0. && max_dy.is_finite() && max_dx.is_finite(),
+            "the maximum shift must be a non-negative number",
+        )?;
+        Ok(Self { max_dy, max_dx })
+    }
+}
+
+impl Transform for RandomTranslation {
+    fn apply(&self, image: &Array2<f64>, rng: &mut StdRng) -> Array2<f64> {
+        let ty = uniform(rng, -self.max_dy, self.max_dy);
+        let tx = uniform(rng, -self.max_dx, self.max_dx);
+        affine(image, 0., 1., ty, tx)
+    }
+}
+
+/// `RandomRotation` rotates the image around its center by up to `max_degrees` in either direction
+#[derive(Debug, Clone, Copy)]
+pub struct RandomRotation {
+    max_degrees: f64,
+}
+
+impl RandomRotation {
+    /// `RandomRotation` constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `max_degrees` - The maximum angle in degrees. It must be non-negative.
+    pub fn new(max_degrees: f64) -> io::Result<Self> {
+        check(
+            max_degrees >= 0. && max_degrees.is_finite(),
+            "the maximum angle must be a non-negative number",
+        )?;
+        Ok(Self { max_degrees })
+    }
+}
+
+impl Transform for RandomRotation {
+    fn apply(&self, image: &Array2<f64>, rng: &mut StdRng) -> Array2<f64> {
+        let degrees = uniform(rng, -self.max_degrees, self.max_degrees);
+        affine(image, degrees.to_radians(), 1., 0., 0.)
+    }
+}
+
+/// `RandomScaling` zooms the image around its center by the factor between `min` and `max`
+#[derive(Debug, Clone, Copy)]
+pub struct RandomScaling {
+    min: f64,
+    max: f64,
+}
+
+impl RandomScaling {
+    /// `RandomScaling` constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `min` - The minimum factor. It must be positive.
+    /// * `max` - The maximum factor. It must not be less than `min`.
+    pub fn new(min: f64, max: f64) -> io::Result<Self> {
+        check(
+            min > 0. && min <= max && max.is_finite(),
+            "the factors must satisfy 0 < min <= max",
+        )?;
+        Ok(Self { min, max })
+    }
+}
+
+impl Transform for RandomScaling {
+    fn apply(&self, image: &Array2<f64>, rng: &mut StdRng) -> Array2<f64> {
+        let scale = uniform(rng, self.min, self.max);
+        affine(image, 0., scale, 0., 0.)
+    }
+}
+
+/// `ElasticDistortion` moves each pixel by the random displacement field
+/// smoothed by the Gaussian filter of `sigma` and scaled by `alpha`.
+/// See also: Simard, Steinkraus and Platt,
+/// "Best Practices for Convolutional Neural Networks Applied to Visual Document Analysis" (2003)
+#[derive(Debug, Clone, Copy)]
+pub struct ElasticDistortion {
+    alpha: f64,
+    sigma: f64,
+}
+
+impl ElasticDistortion {
+    /// `ElasticDistortion` constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `alpha` - The intensity of the displacement in pixels (e.g. 34 for MNIST)
+    /// * `sigma` - The standard deviation of the Gaussian filter in pixels (e.g. 4 for MNIST).
+    ///   It must be positive.
+    pub fn new(alpha: f64, sigma: f64) -> io::Result<Self> {
+        check(alpha.is_finite(), "alpha must be a finite number")?;
+        check(
+            sigma > 0. && sigma.is_finite(),
+            "sigma must be a positive number",
+        )?;
+        Ok(Self { alpha, sigma })
+    }
+
+    fn field(&self, dim: (usize, usize), rng: &mut StdRng) -> Array2<f64> {
+        let noise = Array2::from_shape_fn(dim, |_| rng.gen_range(-1., 1.));
+        let radius = (3. * self.sigma).ceil().max(1.) as isize;
+        let kernel = (-radius..=radius)
+            .map(|i| {
+                // Dividing first keeps the center 1 even if `sigma * sigma` underflows.
+                let x = i as f64 / self.sigma;
+                (-0.5 * x * x).exp()
+            })
+            .collect::<Vec<_>>();
+        let sum = kernel.iter().sum::<f64>();
+        let blur = |a: &Array2<f64>, axis: Axis| -> Array2<f64> {
+            let len = a.len_of(axis) as isize;
+            Array2::from_shape_fn(a.dim(), |(r, c)| {
+                kernel
+                    .iter()
+                    .zip(-radius..=radius)
+                    .map(|(k, d)| {
+                        let (r, c) = if axis == Axis(0) {
+                            (r as isize + d, c as isize)
+                        } else {
+                            (r as isize, c as isize + d)
+                        };
+                        let i = if axis == Axis(0) { r } else { c };
+                        if i < 0 || i >= len {
+                            0.
+                        } else {
+                            k * a[[r as usize, c as usize]]
+                        }
+                    })
+                    .sum::<f64>()
+                    / sum
+            })
+        };
+        blur(&blur(&noise, Axis(0)), Axis(1)) * self.alpha
+    }
+}
+
+impl Transform for ElasticDistortion {
+    fn apply(&self, image: &Array2<f64>, rng: &mut StdRng) -> Array2<f64> {
+        let dy = self.field(image.dim(), rng);
+        let dx = self.field(image.dim(), rng);
+        warp(image, |y, x| {
+            let (r, c) = (y as usize, x as usize);
+            (y + dy[[r, c]], x + dx[[r, c]])
+        })
+    }
+}
+
+/// `GaussianNoise` adds the Gaussian noise of standard deviation `std` to each pixel
+#[derive(Debug, Clone, Copy)]
+pub struct GaussianNoise {
+    std: f64,
+}
+
+impl GaussianNoise {
+    /// `GaussianNoise` constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `std` - The standard deviation of the noise. It must be non-negative.
+    pub fn new(std: f64) -> io::Result<Self> {
+        check(
+            std >= 0. && std.is_finite(),
+            "the standard deviation must be a non-negative number",
+        )?;
+        Ok(Self { std })
+    }
+}
+
+impl Transform for GaussianNoise {
+    fn apply(&self, image: &Array2<f64>, rng: &mut StdRng) -> Array2<f64> {
+        image.mapv(|x| x + self.std * standard_normal(rng))
+    }
+}
+
+/// `RandomErasing` fills a random rectangle of the image with `value` in probability `probability`.
+/// The default erases in probability 0.5 the rectangle of 2% to 33% of the image with 0.
+/// See also: Zhong et al., "Random Erasing Data Augmentation" (2017)
+#[derive(Debug, Clone, Copy)]
+pub struct RandomErasing {
+    probability: f64,
+    min_area: f64,
+    max_area: f64,
+    value: f64,
+}
+
+impl RandomErasing {
+    /// `RandomErasing` constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `probability` - The probability that the image is erased, between 0 and 1
+    /// * `min_area` - The minimum ratio of the area of the rectangle to the image. It must be positive.
+    /// * `max_area` - The maximum ratio of the area of the rectangle to the image,
+    ///   between `min_area` and 1
+    /// * `value` - The value of the erased pixels
+    pub fn new(probability: f64, min_area: f64, max_area: f64, value: f64) -> io::Result<Self> {
+        check(
+            (0. ..=1.).contains(&probability),
+            "the probability must be between 0 and 1",
+        )?;
+        check(
+            min_area > 0. && min_area <= max_area && max_area <= 1.,
+            "the ratios of the area must satisfy 0 < min_area <= max_area <= 1",
+        )?;
+        Ok(Self {
+            probability,
+            min_area,
+            max_area,
+            value,
+        })
+    }
+}
+
+impl Default for RandomErasing {
+    fn default() -> Self {
+        Self {
+            probability: 0.5,
+            min_area: 0.02,
+            max_area: 0.33,
+            value: 0.,
+        }
+    }
+}
+
+impl Transform for RandomErasing {
+    fn apply(&self, image: &Array2<f64>, rng: &mut StdRng) -> Array2<f64> {
+        let mut image = image.clone();
+        if rng.gen::<f64>() >= self.probability {
+            return image;
+        }
+        let (rows, cols) = image.dim();
+        // Retry until the rectangle fits in the image
+        for _ in 0..10 {
+            let area = uniform(rng, self.min_area, self.max_area) * (rows * cols) as f64;
+            let ratio = rng.gen_range((0.3f64).ln(), (1. / 0.3f64).ln()).exp();
+            let h = (area * ratio).sqrt().round() as usize;
+            let w = (area / ratio).sqrt().round() as usize;
+            if h == 0 || w == 0 || h > rows || w > cols {
+                continue;
+            }
+            let top = rng.gen_range(0, rows - h + 1);
+            let left = rng.gen_range(0, cols - w + 1);
+            image
+                .slice_mut(s![top..top + h, left..left + w])
+                .fill(self.value);
+            break;
+        }
+        image
+    }
+}
+
+/// `Augmented` applies `transform` to each `MnistImage` of `dataset` when it is taken.
+/// The random number generator of each sample is seeded by the seed, the epoch and the index,
+/// so the result does not depend on the order or the threads the samples are taken in.
+/// `DataLoader::iter` and `PrefetchLoader::iter` pass the epoch of each batch
+/// to `Dataset::get_at`, and `Dataset::get` takes the samples of epoch 0.
+pub struct Augmented<D> {
+    dataset: D,
+    transform: Arc<dyn Transform>,
+    rows: usize,
+    cols: usize,
+    seed: u64,
+}
+
+impl<D: Dataset<Item = MnistImage>> Augmented<D> {
+    /// `Augmented` constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `dataset` - Dataset
+    /// * `transform` - Transformation applied to each image
+    /// * `shape` - The number of rows and columns of an image (e.g. (28, 28) for MNIST)
+    /// * `seed` - The seed of the random number generator
+    pub fn new(
+        dataset: D,
+        transform: Arc<dyn Transform>,
+        shape: (usize, usize),
+        seed: u64,
+    ) -> Self {
+        Self {
+            dataset,
+            transform,
+            rows: shape.0,
+            cols: shape.1,
+            seed,
+        }
+    }
+
+    fn rng(&self, index: usize, epoch: usize) -> StdRng {
+        StdRng::seed_from_u64(
+            self.seed
+                ^ (epoch as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+                ^ (index as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F),
+        )
+    }
+}
+
+impl<D: Dataset<Item = MnistImage>> Dataset for Augmented<D> {
+    type Item = MnistImage;
+
+    fn len(&self) -> usize {
+        self.dataset.len()
+    }
+
+    fn get(&self, index: usize) -> Self::Item {
+        self.get_at(index, 0)
+    }
+
+    fn label(&self, index: usize) -> Option<usize> {
+        self.dataset.label(index)
+    }
+
+    fn get_at(&self, index: usize, epoch: usize) -> Self::Item {
+        let item = self.dataset.get_at(index, epoch);
+        let shape = item.image.dim();
+        let image = item
+            .image
+            .into_shape((self.rows, self.cols))
+            .expect("the shape of the image does not match");
+        let image = self.transform.apply(&image, &mut self.rng(index, epoch));
+        MnistImage {
+            image: image.into_shape(shape).unwrap(),
+            label: item.label,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::data_loader::DataLoader;
+    use super::*;
+    use std::f64::consts::PI;
+
+    fn dot() -> Array2<f64> {
+        let mut image = Array2::zeros((9, 9));
+        image[[4, 4]] = 1.;
+        image
+    }
+
+    #[test]
+    fn test_geometric_transforms() {
+        let image = dot();
+        let shifted = affine(&image, 0., 1., 2., -1.);
+        assert_eq!(shifted[[6, 3]], 1.);
+        let mut line = Array2::zeros((9, 9));
+        line.row_mut(4).fill(1.);
+        let rotated = affine(&line, PI / 2., 1., 0., 0.);
+        assert!((rotated.column(4).sum() - 9.).abs() < 1e-9);
+        let scaled = affine(&image, 0., 2., 0., 0.);
+        assert!(scaled.sum() > image.sum());
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let elastic = ElasticDistortion::new(2., 1.).unwrap();
+        assert_eq!(elastic.apply(&image, &mut rng).dim(), (9, 9));
+    }
+
+    #[test]
+    fn test_random_erasing() {
+        let image = Array2::ones((10, 10));
+        let erasing = RandomErasing::new(1., 0.02, 0.33, 0.).unwrap();
+        let erased = erasing.apply(&image, &mut StdRng::seed_from_u64(3));
+        let n = erased.iter().filter(|&&x| x == 0.).count();
+        assert!((1..=40).contains(&n), "{}", n);
+    }
+
+    #[test]
+    fn test_degenerate_parameters() {
+        assert!(RandomTranslation::new(-1., 0.).is_err());
+        assert!(RandomRotation::new(f64::NAN).is_err());
+        assert!(RandomScaling::new(1.2, 0.8).is_err());
+        assert!(RandomScaling::new(0., 1.).is_err());
+        assert!(ElasticDistortion::new(34., 0.).is_err());
+        assert!(GaussianNoise::new(-0.1).is_err());
+        assert!(RandomErasing::new(1.5, 0.02, 0.33, 0.).is_err());
+        assert!(RandomErasing::new(1., 0.4, 0.2, 0.).is_err());
+
+        // The empty ranges are valid and keep the image as it is.
+        let image = dot();
+        let mut rng = StdRng::seed_from_u64(0);
+        let identity = Compose(vec![
+            Box::new(RandomTranslation::new(0., 0.).unwrap()),
+            Box::new(RandomRotation::new(0.).unwrap()),
+            Box::new(RandomScaling::new(1., 1.).unwrap()),
+            Box::new(GaussianNoise::new(0.).unwrap()),
+        ]);
+        let transformed = identity.apply(&image, &mut rng);
+        assert!(transformed
+            .iter()
+            .zip(image.iter())
+            .all(|(a, b)| (a - b).abs() < 1e-6));
+        let erased = RandomErasing::new(1., 0.5, 0.5, 0.)
+            .unwrap()
+            .apply(&Array2::ones((10, 10)), &mut rng);
+        assert!(erased.iter().any(|&x| x == 0.));
+        for &sigma in &[1e-3, 1e-200] {
+            let elastic = ElasticDistortion::new(34., sigma).unwrap();
+            assert!(elastic
+                .apply(&image, &mut rng)
+                .iter()
+                .all(|x| x.is_finite()));
+        }
+    }
+
+    #[test]
+    fn test_augmented_is_deterministic() {
+        let data = (0..4)
+            .map(|i| MnistImage {
+                image: dot().into_shape((1, 81)).unwrap(),
+                label: i,
+            })
+            .collect::<Vec<_>>();
+        let transform = Arc::new(Compose(vec![
+            Box::new(RandomRotation::new(30.).unwrap()),
+            Box::new(GaussianNoise::new(0.1).unwrap()),
+        ]));
+        let augmented = Augmented::new(data, transform, (9, 9), 5);
+        let loader = DataLoader::new(&augmented, 2).unwrap();
+        let epoch0 = loader
+            .iter(0)
+            .unwrap()
+            .map(|b| b.images)
+            .collect::<Vec<_>>();
+        let epoch1 = loader
+            .iter(1)
+            .unwrap()
+            .map(|b| b.images)
+            .collect::<Vec<_>>();
+        assert_ne!(epoch0, epoch1);
+        assert_eq!(
+            loader
+                .iter(0)
+                .unwrap()
+                .map(|b| b.images)
+                .collect::<Vec<_>>(),
+            epoch0
+        );
+        assert_eq!(epoch0[0].dim(), (2, 81));
+
+        // The iterators over different epochs alive at the same time do not affect each other.
+        let (mut it0, it1) = (loader.iter(0).unwrap(), loader.iter(1).unwrap());
+        let first = it0.next().unwrap().images;
+        assert_eq!(it1.map(|b| b.images).collect::<Vec<_>>(), epoch1);
+        assert_eq!(first, epoch0[0]);
+        assert_eq!(it0.next().unwrap().images, epoch0[1]);
+        assert_eq!(augmented.get(0).image, epoch0[0].slice(s![0..1, ..]));
+    }
+}
@@ -0,0 +1,369 @@
+use super::super::utils::fetch_client::{
+    ArchiveKind, ClientOptions, FConf, FetchClient, HashAlgorithm, RemoteFile,
+};
+use super::super::utils::natural_transform::to_io;
+use super::mnist::DatasetKey;
+use ndarray::{Array1, Array4};
+use std::fs;
+use std::io;
+
+const URL_BASE: &str = "https://www.cs.toronto.edu/~kriz/";
+const CIFAR10_SAVE_DIR: &str = ".cifar10";
+const CIFAR100_SAVE_DIR: &str = ".cifar100";
+
+/// The number of channels of an image
+pub const CHANNELS: usize = 3;
+/// The number of rows of an image
+pub const ROWS: usize = 32;
+/// The number of columns of an image
+pub const COLS: usize = 32;
+const IMAGE_SIZE: usize = CHANNELS * ROWS * COLS;
+
+static CIFAR10_FILES: [RemoteFile; 1] = [RemoteFile {
+    host_and_path: URL_BASE,
+    fname: "cifar-10-binary.tar.gz",
+    digest: "c32a1d4ab5d03f1284b67883e8d87530",
+    algorithm: HashAlgorithm::Md5,
+    query: "",
+    archive: Some(ArchiveKind::TarGz),
+}];
+
+static CIFAR100_FILES: [RemoteFile; 1] = [RemoteFile {
+    host_and_path: URL_BASE,
+    fname: "cifar-100-binary.tar.gz",
+    digest: "03b5dce01913d631647c71ecec9e9cb8",
+    algorithm: HashAlgorithm::Md5,
+    query: "",
+    archive: Some(ArchiveKind::TarGz),
+}];
+
+const CIFAR10_TRAIN: [&str; 5] = [
+    "cifar-10-batches-bin/data_batch_1.bin",
+    "cifar-10-batches-bin/data_batch_2.bin",
+    "cifar-10-batches-bin/data_batch_3.bin",
+    "cifar-10-batches-bin/data_batch_4.bin",
+    "cifar-10-batches-bin/data_batch_5.bin",
+];
+const CIFAR10_TEST: [&str; 1] = ["cifar-10-batches-bin/test_batch.bin"];
+const CIFAR100_TRAIN: [&str; 1] = ["cifar-100-binary/train.bin"];
+const CIFAR100_TEST: [&str; 1] = ["cifar-100-binary/test.bin"];
+
+/// The class names of CIFAR-10 indexed by the label
+pub const CIFAR10_CLASSES: [&str; 10] = [
+    "airplane",
+    "automobile",
+    "bird",
+    "cat",
+    "deer",
+    "dog",
+    "frog",
+    "horse",
+    "ship",
+    "truck",
+];
+
+/// The names of the superclasses of CIFAR-100 indexed by the coarse label
+pub const CIFAR100_COARSE_CLASSES: [&str; 20] = [
+    "aquatic_mammals",
+    "fish",
+    "flowers",
+    "food_containers",
+    "fruit_and_vegetables",
+    "household_electrical_devices",
+    "household_furniture",
+    "insects",
+    "large_carnivores",
+    "large_man-made_outdoor_things",
+    "large_natural_outdoor_scenes",
+    "large_omnivores_and_herbivores",
+    "medium_mammals",
+    "non-insect_invertebrates",
+    "people",
+    "reptiles",
+    "small_mammals",
+    "trees",
+    "vehicles_1",
+    "vehicles_2",
+];
+
+/// The class names of CIFAR-100 indexed by the fine label
+pub const CIFAR100_FINE_CLASSES: [&str; 100] = [
+    "apple",
+    "aquarium_fish",
+    "baby",
+    "bear",
+    "beaver",
+    "bed",
+    "bee",
+    "beetle",
+    "bicycle",
+    "bottle",
+    "bowl",
+    "boy",
+    "bridge",
+    "bus",
+    "butterfly",
+    "camel",
+    "can",
+    "castle",
+    "caterpillar",
+    "cattle",
+    "chair",
+    "chimpanzee",
+    "clock",
+    "cloud",
+    "cockroach",
+    "couch",
+    "crab",
+    "crocodile",
+    "cup",
+    "dinosaur",
+    "dolphin",
+    "elephant",
+    "flatfish",
+    "forest",
+    "fox",
+    "girl",
+    "hamster",
+    "house",
+    "kangaroo",
+    "keyboard",
+    "lamp",
+    "lawn_mower",
+    "leopard",
+    "lion",
+    "lizard",
+    "lobster",
+    "man",
+    "maple_tree",
+    "motorcycle",
+    "mountain",
+    "mouse",
+    "mushroom",
+    "oak_tree",
+    "orange",
+    "orchid",
+    "otter",
+    "palm_tree",
+    "pear",
+    "pickup_truck",
+    "pine_tree",
+    "plain",
+    "plate",
+    "poppy",
+    "porcupine",
+    "possum",
+    "rabbit",
+    "raccoon",
+    "ray",
+    "road",
+    "rocket",
+    "rose",
+    "sea",
+    "seal",
+    "shark",
+    "shrew",
+    "skunk",
+    "skyscraper",
+    "snail",
+    "snake",
+    "spider",
+    "squirrel",
+    "streetcar",
+    "sunflower",
+    "sweet_pepper",
+    "table",
+    "tank",
+    "telephone",
+    "television",
+    "tiger",
+    "tractor",
+    "train",
+    "trout",
+    "tulip",
+    "turtle",
+    "wardrobe",
+    "whale",
+    "willow_tree",
+    "wolf",
+    "woman",
+    "worm",
+];
+
+/// `CifarImages` holds the decoded images and labels of CIFAR-10 or CIFAR-100
+#[derive(Debug, Clone)]
+pub struct CifarImages {
+    /// Images of shape (N, 3, 32, 32), whose channels are red, green and blue
+    pub images: Array4<f64>,
+    /// Labels of shape (N). The fine labels for CIFAR-100.
+    pub labels: Array1<u8>,
+    /// The coarse labels of shape (N) for CIFAR-100
+    pub coarse_labels: Option<Array1<u8>>,
+}
+
+impl CifarImages {
+    /// `len` returns the number of images
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// `is_empty` checks if there are no images
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+}
+
+/// `cifar10_fetch_client` constructs the `FetchClient` of CIFAR-10
+pub fn cifar10_fetch_client(opts: &ClientOptions) -> io::Result<FetchClient<'static>> {
+    FetchClient::with_options(FConf::new(CIFAR10_SAVE_DIR, CIFAR10_FILES.iter()), opts)
+}
+
+/// `cifar100_fetch_client` constructs the `FetchClient` of CIFAR-100
+pub fn cifar100_fetch_client(opts: &ClientOptions) -> io::Result<FetchClient<'static>> {
+    FetchClient::with_options(FConf::new(CIFAR100_SAVE_DIR, CIFAR100_FILES.iter()), opts)
+}
+
+/// Loading CIFAR-10 (<https://www.cs.toronto.edu/~kriz/cifar.html>).
+/// If `cifar-10-binary.tar.gz` is not found in the `cifar10` directory of the cache root,
+/// download and extract it, and decode the binary version of the data.
+/// The class names are `CIFAR10_CLASSES`.
+///
+/// # Arguments
+///
+/// * `dataset_key` - `mnist::train_dataset()` or `mnist::test_dataset()`.
+/// * `normalize` - Flag that determines whether the image is normalized between 0.0 and 1.0.
+pub fn load_cifar10(dataset_key: DatasetKey, normalize: bool) -> io::Result<CifarImages> {
+    load_cifar10_with(dataset_key, normalize, &ClientOptions::default())
+}
+
+/// `load_cifar10_with` is the same as `load_cifar10`
+/// except that the download and decoding are set up by `opts`
+pub fn load_cifar10_with(
+    dataset_key: DatasetKey,
+    normalize: bool,
+    opts: &ClientOptions,
+) -> io::Result<CifarImages> {
+    let client = cifar10_fetch_client(opts)?;
+    let files: &[&str] = if dataset_key.is_train() {
+        &CIFAR10_TRAIN
+    } else {
+        &CIFAR10_TEST
+    };
+    load(&client, files, 1, normalize)
+}
+
+/// Loading CIFAR-100 (<https://www.cs.toronto.edu/~kriz/cifar.html>).
+/// If `cifar-100-binary.tar.gz` is not found in the `cifar100` directory of the cache root,
+/// download and extract it, and decode the binary version of the data.
+/// The labels are the fine labels (`CIFAR100_FINE_CLASSES`) and
+/// the coarse labels (`CIFAR100_COARSE_CLASSES`) are stored in `coarse_labels`.
+///
+/// # Arguments
+///
+/// * `dataset_key` - `mnist::train_dataset()` or `mnist::test_dataset()`.
+/// * `normalize` - Flag that determines whether the image is normalized between 0.0 and 1.0.
+pub fn load_cifar100(dataset_key: DatasetKey, normalize: bool) -> io::Result<CifarImages> {
+    load_cifar100_with(dataset_key, normalize, &ClientOptions::default())
+}
+
+/// `load_cifar100_with` is the same as `load_cifar100`
+/// except that the download and decoding are set up by `opts`
+pub fn load_cifar100_with(
+    dataset_key: DatasetKey,
+    normalize: bool,
+    opts: &ClientOptions,
+) -> io::Result<CifarImages> {
+    let client = cifar100_fetch_client(opts)?;
+    let files: &[&str] = if dataset_key.is_train() {
+        &CIFAR100_TRAIN
+    } else {
+        &CIFAR100_TEST
+    };
+    load(&client, files, 2, normalize)
+}
+
+fn load(
+    client: &FetchClient,
+    files: &[&str],
+    label_bytes: usize,
+    normalize: bool,
+) -> io::Result<CifarImages> {
+    client.get()?;
+    let mut data = Vec::new();
+    for fname in files {
+        client.progress().decode_start(fname);
+        data.extend(fs::read(client.dir_client.file_path(fname))?);
+        client.progress().decode_finish(fname);
+    }
+    parse_records(&data, label_bytes, normalize)
+}
+
+/// `parse_records` decodes the records of the binary version of CIFAR.
+/// Each record consists of `label_bytes` labels (the label of CIFAR-10,
+/// or the coarse and fine labels of CIFAR-100) followed by
+/// 1024 red, 1024 green and 1024 blue pixels in row-major order.
+fn parse_records(data: &[u8], label_bytes: usize, normalize: bool) -> io::Result<CifarImages> {
+    let record_size = label_bytes + IMAGE_SIZE;
+    let records = data.chunks_exact(record_size);
+    if !records.remainder().is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "the size of CIFAR data ({}) is not a multiple of the record size ({})",
+                data.len(),
+                record_size
+            ),
+        ));
+    }
+    let n = data.len() / record_size;
+    let scale = if normalize { 255. } else { 1. };
+    let mut images = Vec::with_capacity(n * IMAGE_SIZE);
+    let mut labels = Vec::with_capacity(n);
+    let mut coarse_labels = Vec::with_capacity(n);
+    for record in records {
+        let (label, image) = record.split_at(label_bytes);
+        labels.push(label[label_bytes - 1]);
+        if label_bytes > 1 {
+            coarse_labels.push(label[0]);
+        }
+        images.extend(image.iter().map(|&x| f64::from(x) / scale));
+    }
+    Ok(CifarImages {
+        images: to_io(
+            Array4::from_shape_vec((n, CHANNELS, ROWS, COLS), images),
+            io::ErrorKind::InvalidData,
+        )?,
+        labels: Array1::from(labels),
+        coarse_labels: if label_bytes > 1 {
+            Some(Array1::from(coarse_labels))
+        } else {
+            None
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_records() {
+        let mut data = Vec::new();
+        for (coarse, fine) in [(3u8, 42u8), (19, 99)].iter() {
+            data.push(*coarse);
+            data.push(*fine);
+            data.extend((0..IMAGE_SIZE).map(|i| (i % 256) as u8));
+        }
+        let cifar = parse_records(&data, 2, true).unwrap();
+        assert_eq!(cifar.images.dim(), (2, CHANNELS, ROWS, COLS));
+        assert_eq!(cifar.labels.to_vec(), vec![42, 99]);
+        assert_eq!(cifar.coarse_labels.unwrap().to_vec(), vec![3, 19]);
+        // The first pixel of the green channel
+        assert_eq!(cifar.images[[1, 1, 0, 0]], (1024 % 256) as f64 / 255.);
+        assert_eq!(cifar.images[[0, 0, 1, 2]], 34. / 255.);
+
+        let cifar = parse_records(&data, 1, false).unwrap_err();
+        assert_eq!(cifar.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(CIFAR100_FINE_CLASSES[99], "worm");
+    }
+}
@@ -0,0 +1,472 @@
+extern crate rand;
+
+use super::mnist::{Batched, MnistImage, MnistTensor};
+use ndarray::{stack, Axis};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::HashMap;
+use std::io;
+use std::sync::Arc;
+use std::vec;
+
+/// `Dataset` is a collection of samples accessed by index
+pub trait Dataset {
+    /// The type of a sample
+    type Item;
+
+    /// `len` returns the number of samples
+    fn len(&self) -> usize;
+
+    /// `is_empty` checks if there are no samples
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// `get` returns the `index`th sample.
+    ///
+    /// # Panics
+    ///
+    /// May panic if `index` is out of range.
+    fn get(&self, index: usize) -> Self::Item;
+
+    /// `label` returns the class of the `index`th sample if the dataset is labeled.
+    /// It is used by `Sampler::ClassBalanced`.
+    fn label(&self, _index: usize) -> Option<usize> {
+        None
+    }
+
+    /// `get_at` returns the `index`th sample as it is taken in `epoch`.
+    /// The loaders take the samples by it, passing the epoch of the batch explicitly,
+    /// so the iterators over different epochs can be alive at the same time.
+    /// The datasets which change every epoch (e.g. `augment::Augmented`) override it.
+    fn get_at(&self, index: usize, _epoch: usize) -> Self::Item {
+        self.get(index)
+    }
+}
+
+impl<D: Dataset + ?Sized> Dataset for &D {
+    type Item = D::Item;
+
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+
+    fn get(&self, index: usize) -> Self::Item {
+        (**self).get(index)
+    }
+
+    fn label(&self, index: usize) -> Option<usize> {
+        (**self).label(index)
+    }
+
+    fn get_at(&self, index: usize, epoch: usize) -> Self::Item {
+        (**self).get_at(index, epoch)
+    }
+}
+
+impl<D: Dataset + ?Sized> Dataset for Arc<D> {
+    type Item = D::Item;
+
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+
+    fn get(&self, index: usize) -> Self::Item {
+        (**self).get(index)
+    }
+
+    fn label(&self, index: usize) -> Option<usize> {
+        (**self).label(index)
+    }
+
+    fn get_at(&self, index: usize, epoch: usize) -> Self::Item {
+        (**self).get_at(index, epoch)
+    }
+}
+
+impl Dataset for vec::Vec<MnistImage> {
+    type Item = MnistImage;
+
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    fn get(&self, index: usize) -> Self::Item {
+        self[index].clone()
+    }
+
+    fn label(&self, index: usize) -> Option<usize> {
+        Some(self[index].label as usize)
+    }
+}
+
+impl Dataset for MnistTensor {
+    type Item = MnistImage;
+
+    fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    fn get(&self, index: usize) -> Self::Item {
+        MnistImage {
+            image: self.images.row(index).insert_axis(Axis(0)).to_owned(),
+            label: self.labels[index],
+        }
+    }
+
+    fn label(&self, index: usize) -> Option<usize> {
+        Some(self.labels[index] as usize)
+    }
+}
+
+/// `Collate` merges the samples into a batch
+pub trait Collate: Sized {
+    /// The type of a batch
+    type Batch;
+
+    /// `collate` merges `items` into a batch
+    fn collate(items: vec::Vec<Self>) -> Self::Batch;
+}
+
+impl Collate for MnistImage {
+    type Batch = Batched;
+
+    fn collate(items: vec::Vec<Self>) -> Self::Batch {
+        let images = items.iter().map(|x| x.image.view()).collect::<Vec<_>>();
+        Batched::new(
+            stack(Axis(0), &images).expect("the images must have the same shape"),
+            items.iter().map(|x| x.label).collect(),
+        )
+    }
+}
+
+/// `Sampler` decides the order of the samples in an epoch
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sampler {
+    /// In the order of the index
+    Sequential,
+    /// Random permutation of the indices, which is shuffled every epoch
+    Random,
+    /// `len` samples drawn with replacement,
+    /// where the probability of each index is proportional to its weight
+    Weighted(vec::Vec<f64>),
+    /// `len` samples drawn with replacement,
+    /// where every class is drawn with the same probability.
+    /// It requires `Dataset::label`.
+    ClassBalanced,
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Sampler::Sequential
+    }
+}
+
+/// `DataLoader` yields the batches of `Dataset`.
+///
+/// # Examples
+///
+/// ```
+/// use deep_learning_playground::setup::data_loader::{DataLoader, Sampler};
+/// use deep_learning_playground::setup::mnist::MnistImage;
+/// use ndarray::Array2;
+///
+/// let data = (0..10)
+///     .map(|i| MnistImage { image: Array2::zeros((1, 4)), label: i % 3 })
+///     .collect::<Vec<_>>();
+/// let loader = DataLoader::new(&data, 4)
+///     .unwrap()
+///     .sampler(Sampler::Random)
+///     .seed(42);
+/// for epoch in 0..2 {
+///     let sizes = loader
+///         .iter(epoch)
+///         .unwrap()
+///         .map(|b| b.batch_size())
+///         .collect::<Vec<_>>();
+///     assert_eq!(sizes, vec![4, 4, 2]);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DataLoader<D> {
+    dataset: D,
+    batch_size: usize,
+    drop_last: bool,
+    sampler: Sampler,
+    seed: u64,
+}
+
+impl<D: Dataset> DataLoader<D> {
+    /// `DataLoader` constructor.
+    /// The samples are taken sequentially and the last batch may be smaller than `batch_size`.
+    ///
+    /// # Arguments
+    ///
+    /// * `dataset` - Dataset
+    /// * `batch_size` - The number of samples in a batch. It must be non-zero.
+    pub fn new(dataset: D, batch_size: usize) -> io::Result<Self> {
+        if batch_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The batch size must be non-zero",
+            ));
+        }
+        Ok(Self {
+            dataset,
+            batch_size,
+            drop_last: false,
+            sampler: Sampler::Sequential,
+            seed: 0,
+        })
+    }
+
+    /// `shuffle` switches the sampler to `Sampler::Random` or `Sampler::Sequential`
+    pub fn shuffle(self, shuffle: bool) -> Self {
+        self.sampler(if shuffle {
+            Sampler::Random
+        } else {
+            Sampler::Sequential
+        })
+    }
+
+    /// `sampler` sets the sampling strategy
+    pub fn sampler(mut self, sampler: Sampler) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    /// `drop_last` sets whether the last batch smaller than the batch size is dropped
+    pub fn drop_last(mut self, drop_last: bool) -> Self {
+        self.drop_last = drop_last;
+        self
+    }
+
+    /// `seed` sets the seed of the random number generator.
+    /// The same seed and epoch always give the same order.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// `dataset` returns the dataset
+    pub fn dataset(&self) -> &D {
+        &self.dataset
+    }
+
+    /// `batch_size` returns the number of samples in a batch
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    /// `len` returns the number of batches in an epoch
+    pub fn len(&self) -> usize {
+        let n = self.dataset.len();
+        if self.drop_last {
+            n / self.batch_size
+        } else {
+            (n + self.batch_size - 1) / self.batch_size
+        }
+    }
+
+    /// `is_empty` checks if an epoch has no batches
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// `rng` returns the random number generator of `epoch`
+    pub fn rng(&self, epoch: usize) -> StdRng {
+        // Spread the epochs so that the neighboring seeds do not share their streams.
+        StdRng::seed_from_u64(
+            self.seed
+                .wrapping_add((epoch as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)),
+        )
+    }
+
+    /// `indices` returns the order of the samples in `epoch`
+    pub fn indices(&self, epoch: usize) -> io::Result<vec::Vec<usize>> {
+        let n = self.dataset.len();
+        let mut rng = self.rng(epoch);
+        match &self.sampler {
+            Sampler::Sequential => Ok((0..n).collect()),
+            Sampler::Random => {
+                let mut indices = (0..n).collect::<Vec<_>>();
+                indices.shuffle(&mut rng);
+                Ok(indices)
+            }
+            Sampler::Weighted(weights) => {
+                if weights.len() != n {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "the number of weights ({}) does not match the size of the dataset ({})",
+                            weights.len(),
+                            n
+                        ),
+                    ));
+                }
+                draw(weights, n, &mut rng)
+            }
+            Sampler::ClassBalanced => {
+                let labels = (0..n)
+                    .map(|i| self.dataset.label(i))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "class-balanced sampling requires a labeled dataset",
+                        )
+                    })?;
+                let mut counts = HashMap::new();
+                for &label in labels.iter() {
+                    *counts.entry(label).or_insert(0usize) += 1;
+                }
+                let weights = labels
+                    .iter()
+                    .map(|label| 1. / counts[label] as f64)
+                    .collect::<Vec<_>>();
+                draw(&weights, n, &mut rng)
+            }
+        }
+    }
+
+    /// `batch_indices` splits the order of the samples in `epoch` into batches
+    pub fn batch_indices(&self, epoch: usize) -> io::Result<vec::Vec<vec::Vec<usize>>> {
+        let mut batches = self
+            .indices(epoch)?
+            .chunks(self.batch_size)
+            .map(|c| c.to_vec())
+            .collect::<Vec<_>>();
+        if self.drop_last && batches.last().map_or(false, |b| b.len() < self.batch_size) {
+            batches.pop();
+        }
+        Ok(batches)
+    }
+
+    /// `iter` iterates over the batches of `epoch`
+    pub fn iter(&self, epoch: usize) -> io::Result<Batches<'_, D>>
+    where
+        D::Item: Collate,
+    {
+        Ok(Batches {
+            dataset: &self.dataset,
+            batches: self.batch_indices(epoch)?.into_iter(),
+            epoch,
+        })
+    }
+}
+
+fn draw(weights: &[f64], n: usize, rng: &mut StdRng) -> io::Result<vec::Vec<usize>> {
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+    let dist = WeightedIndex::new(weights)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+    Ok((0..n).map(|_| dist.sample(rng)).collect())
+}
+
+/// The iterator over the batches of an epoch created by `DataLoader::iter`
+pub struct Batches<'a, D> {
+    dataset: &'a D,
+    batches: vec::IntoIter<vec::Vec<usize>>,
+    epoch: usize,
+}
+
+impl<'a, D> Iterator for Batches<'a, D>
+where
+    D: Dataset,
+    D::Item: Collate,
+{
+    type Item = <D::Item as Collate>::Batch;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (dataset, epoch) = (self.dataset, self.epoch);
+        self.batches.next().map(|indices| {
+            D::Item::collate(
+                indices
+                    .into_iter()
+                    .map(|i| dataset.get_at(i, epoch))
+                    .collect(),
+            )
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.batches.size_hint()
+    }
+}
+
+impl<'a, D> ExactSizeIterator for Batches<'a, D>
+where
+    D: Dataset,
+    D::Item: Collate,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array2;
+
+    fn dataset(labels: &[u8]) -> Vec<MnistImage> {
+        labels
+            .iter()
+            .enumerate()
+            .map(|(i, &label)| MnistImage {
+                image: Array2::from_elem((1, 2), i as f64),
+                label,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_remainder() {
+        let data = dataset(&[0; 10]);
+        let loader = DataLoader::new(&data, 4).unwrap();
+        let batches = loader.iter(0).unwrap().collect::<Vec<_>>();
+        assert_eq!(batches.len(), 3);
+        assert_eq!(batches[2].images.dim(), (2, 2));
+        assert_eq!(batches[2].images[[1, 0]], 9.);
+
+        let loader = loader.drop_last(true);
+        assert_eq!(loader.len(), 2);
+        assert_eq!(loader.iter(0).unwrap().count(), 2);
+        assert!(DataLoader::new(&data, 0).is_err());
+    }
+
+    #[test]
+    fn test_seeded_shuffle() {
+        let data = dataset(&[0; 100]);
+        let loader = DataLoader::new(&data, 10).unwrap().shuffle(true).seed(7);
+        let first = loader.indices(0).unwrap();
+        assert_eq!(first, loader.indices(0).unwrap());
+        assert_ne!(first, loader.indices(1).unwrap());
+        assert_ne!(first, (0..100).collect::<Vec<_>>());
+        let mut sorted = first.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_weighted_samplers() {
+        let data = dataset(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        let loader = DataLoader::new(&data, 5)
+            .unwrap()
+            .sampler(Sampler::Weighted(vec![
+                0., 0., 0., 0., 0., 0., 0., 0., 0., 1.,
+            ]));
+        assert_eq!(loader.indices(0).unwrap(), vec![9; 10]);
+
+        let loader = loader.sampler(Sampler::ClassBalanced).seed(1);
+        let minority = (0..50)
+            .flat_map(|epoch| loader.indices(epoch).unwrap())
+            .filter(|&i| i == 9)
+            .count();
+        // Each class is drawn with probability 1/2.
+        assert!(minority > 200 && minority < 300, "{}", minority);
+
+        let loader = loader.sampler(Sampler::Weighted(vec![1.]));
+        assert!(loader.indices(0).is_err());
+    }
+}
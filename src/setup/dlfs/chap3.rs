@@ -1,4 +1,6 @@
-use super::super::super::utils::fetch_client::{FConf, FetchClient, RemoteFile};
+use super::super::super::utils::fetch_client::{
+    block_on, ClientOptions, FConf, FetchClient, HashAlgorithm, RemoteFile,
+};
 use super::super::super::utils::natural_transform::to_io;
 use ndarray::Array2;
 use numpy::{PyArray1, PyArray2};
@@ -70,26 +72,55 @@ fn deserialize<'py>(
     )))
 }
 
+static FILES: [RemoteFile; 1] = [RemoteFile {
+    host_and_path: URL_BASE,
+    fname: FILE_NAME,
+    digest: "b7f55a27988ba34c3777b0f1bbd464817c8a4db855723e6ff26f703501917a13",
+    algorithm: HashAlgorithm::Sha256,
+    query: "raw=true",
+    archive: None,
+}];
+
+/// `fetch_client` constructs the `FetchClient` of the trained parameters.
+pub fn fetch_client(opts: &ClientOptions) -> io::Result<FetchClient<'static>> {
+    FetchClient::with_options(FConf::new(WEIGHT_SAVE_DIR, FILES.iter()), opts)
+}
+
 /// `load_trained_params` loads trained parameters from pickle
 /// ([oreilly-japan/deep-learning-from-scratch/ch03/sample_weight.pkl](https://github.com/oreilly-japan/deep-learning-from-scratch/blob/0dda3d1715e2431b76eb4089b60881948853ba2a/ch03/sample_weight.pkl)).
 /// It requires some python packages. E.g. python3-dev, python-dev (On Ubuntu 18.04)
 /// and `numpy`.
 pub fn load_trained_params() -> io::Result<Chap3Param> {
-    let file = [RemoteFile::new(
-        URL_BASE,
-        FILE_NAME,
-        "b7f55a27988ba34c3777b0f1bbd464817c8a4db855723e6ff26f703501917a13",
-        "raw=true",
-    )];
-
-    let client = FetchClient::new(FConf::new(WEIGHT_SAVE_DIR, file.iter()))?;
-    client.get()?;
-
-    let gil = Python::acquire_gil();
-    deserialize(gil.python(), &client, FILE_NAME).map_err(|e| {
-        io::Error::new(
-            io::ErrorKind::Other,
-            format!("Python interpreter error: {:?}", e),
-        )
-    })?
+    load_trained_params_with(&ClientOptions::default())
+}
+
+/// `load_trained_params_with` is the same as `load_trained_params`
+/// except that the download is set up by `opts`.
+pub fn load_trained_params_with(opts: &ClientOptions) -> io::Result<Chap3Param> {
+    block_on(load_trained_params_with_async(opts))
+}
+
+/// `load_trained_params_async` is the same as `load_trained_params`
+/// except that it runs on the runtime of the caller instead of creating its own.
+/// The Python interpreter runs on the blocking threads of the runtime.
+pub async fn load_trained_params_async() -> io::Result<Chap3Param> {
+    load_trained_params_with_async(&ClientOptions::default()).await
+}
+
+/// `load_trained_params_with_async` is the same as `load_trained_params_async`
+/// except that the download is set up by `opts`.
+pub async fn load_trained_params_with_async(opts: &ClientOptions) -> io::Result<Chap3Param> {
+    let client = fetch_client(opts)?;
+    client.get_async().await?;
+
+    tokio::task::spawn_blocking(move || {
+        let gil = Python::acquire_gil();
+        deserialize(gil.python(), &client, FILE_NAME).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                format!("Python interpreter error: {:?}", e),
+            )
+        })?
+    })
+    .await?
 }
@@ -0,0 +1,296 @@
+use super::super::utils::natural_transform::to_io;
+use super::idx::{self, IdxData};
+use super::mnist::MnistTensor;
+use ndarray::Axis;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::vec;
+
+/// `Selection` chooses the images to be exported
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selection {
+    /// The range of the indices of the images. All the images if `None`.
+    pub range: Option<Range<usize>>,
+    /// The classes of the images. All the classes if `None`.
+    pub classes: Option<vec::Vec<u8>>,
+}
+
+impl Selection {
+    /// `Selection` constructor which selects all the images
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `range` selects the images whose indices are in `range`
+    pub fn range(mut self, range: Range<usize>) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    /// `classes` selects the images whose labels are in `classes`
+    pub fn classes(mut self, classes: &[u8]) -> Self {
+        self.classes = Some(classes.to_vec());
+        self
+    }
+
+    /// `indices` returns the indices of the images of `tensor` selected in ascending order.
+    /// The range is applied before the classes,
+    /// e.g. the images of class 3 among the first 1000 images.
+    pub fn indices(&self, tensor: &MnistTensor) -> vec::Vec<usize> {
+        let range = match self.range {
+            Some(ref r) => r.start.min(tensor.len())..r.end.min(tensor.len()),
+            None => 0..tensor.len(),
+        };
+        range
+            .filter(|&i| {
+                self.classes
+                    .as_ref()
+                    .map_or(true, |classes| classes.contains(&tensor.labels[i]))
+            })
+            .collect()
+    }
+
+    /// `apply` returns the copy of the images of `tensor` selected
+    pub fn apply(&self, tensor: &MnistTensor) -> MnistTensor {
+        let indices = self.indices(tensor);
+        MnistTensor {
+            images: tensor.images.select(Axis(0), &indices),
+            labels: tensor.labels.select(Axis(0), &indices),
+            rows: tensor.rows,
+            cols: tensor.cols,
+        }
+    }
+}
+
+/// `write_csv` writes `tensor` in CSV with a header line.
+/// Each line has the label followed by the pixels (`label,pixel0,pixel1,...`).
+/// The pixels are written in the shortest form which is read back to the same value.
+pub fn write_csv<W: Write>(w: &mut W, tensor: &MnistTensor) -> io::Result<()> {
+    write!(w, "label")?;
+    for i in 0..tensor.rows * tensor.cols {
+        write!(w, ",pixel{}", i)?;
+    }
+    writeln!(w)?;
+    for (image, label) in tensor.images.outer_iter().zip(tensor.labels.iter()) {
+        write!(w, "{}", label)?;
+        for x in image.iter() {
+            write!(w, ",{}", x)?;
+        }
+        writeln!(w)?;
+    }
+    Ok(())
+}
+
+/// `write_npy` writes the array of `shape` in NPY format version 1.0
+///
+/// # Arguments
+///
+/// * `w` - Destination
+/// * `descr` - The type of the elements in NumPy (e.g. `<f8`)
+/// * `shape` - The shape of the array in C order
+/// * `data` - The elements already encoded in `descr`
+pub fn write_npy<W: Write>(w: &mut W, descr: &str, shape: &[usize], data: &[u8]) -> io::Result<()> {
+    let shape = match shape {
+        [n] => format!("({},)", n),
+        _ => format!(
+            "({})",
+            shape
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        descr, shape
+    );
+    // The magic string, the version, the length of the header, the header and
+    // the terminating newline are aligned to 64 bytes.
+    let unpadded = 6 + 2 + 2 + header.len() + 1;
+    header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+    header.push('\n');
+    w.write_all(b"\x93NUMPY\x01\x00")?;
+    w.write_all(&(header.len() as u16).to_le_bytes())?;
+    w.write_all(header.as_bytes())?;
+    w.write_all(data)
+}
+
+/// `write_npz` writes `tensor` in NPZ format, which `numpy.load` reads as
+/// `images` of shape (N, rows, columns) in `float64` and `labels` of shape (N) in `uint8`
+pub fn write_npz<W: Write + Seek>(w: W, tensor: &MnistTensor) -> io::Result<()> {
+    let mut zip = zip::ZipWriter::new(w);
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+
+    to_io(zip.start_file("images.npy", options), io::ErrorKind::Other)?;
+    let mut images = Vec::with_capacity(tensor.images.len() * 8);
+    for x in tensor.images.iter() {
+        images.extend_from_slice(&x.to_le_bytes());
+    }
+    write_npy(
+        &mut zip,
+        "<f8",
+        &[tensor.len(), tensor.rows, tensor.cols],
+        &images,
+    )?;
+
+    to_io(zip.start_file("labels.npy", options), io::ErrorKind::Other)?;
+    write_npy(&mut zip, "|u1", &[tensor.len()], &tensor.labels.to_vec())?;
+
+    to_io(zip.finish(), io::ErrorKind::Other)?;
+    Ok(())
+}
+
+/// `to_idx` converts `tensor` into the IDX data of the images of shape (N, rows, columns)
+/// and the labels of shape (N).
+/// The images are unsigned bytes if all the pixels are integers between 0 and 255
+/// (i.e. not normalized), otherwise doubles.
+pub fn to_idx(tensor: &MnistTensor) -> io::Result<(IdxData, IdxData)> {
+    let shape = (tensor.len(), tensor.rows, tensor.cols);
+    let is_bytes = tensor
+        .images
+        .iter()
+        .all(|&x| x.fract() == 0. && (0. ..=255.).contains(&x));
+    let images = if is_bytes {
+        IdxData::UByte(
+            to_io(
+                tensor.images.mapv(|x| x as u8).into_shape(shape),
+                io::ErrorKind::InvalidData,
+            )?
+            .into_dyn(),
+        )
+    } else {
+        IdxData::Double(
+            to_io(
+                tensor.images.clone().into_shape(shape),
+                io::ErrorKind::InvalidData,
+            )?
+            .into_dyn(),
+        )
+    };
+    Ok((images, IdxData::UByte(tensor.labels.clone().into_dyn())))
+}
+
+/// `export_csv` writes the images of `tensor` chosen by `selection` to the CSV file at `path`
+pub fn export_csv<P: AsRef<Path>>(
+    path: P,
+    tensor: &MnistTensor,
+    selection: &Selection,
+) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    write_csv(&mut w, &selection.apply(tensor))?;
+    w.flush()
+}
+
+/// `export_npz` writes the images of `tensor` chosen by `selection` to the NPZ file at `path`
+pub fn export_npz<P: AsRef<Path>>(
+    path: P,
+    tensor: &MnistTensor,
+    selection: &Selection,
+) -> io::Result<()> {
+    write_npz(File::create(path)?, &selection.apply(tensor))
+}
+
+/// `export_idx` writes the images of `tensor` chosen by `selection` to the IDX files.
+/// The files are compressed by gzip if the extension is `.gz`.
+///
+/// # Arguments
+///
+/// * `images_path` - Path of the image file
+/// * `labels_path` - Path of the label file
+/// * `tensor` - The dataset (e.g. `mnist::load_tensor(train_dataset(), false)`)
+/// * `selection` - The images to be exported
+pub fn export_idx<P: AsRef<Path>>(
+    images_path: P,
+    labels_path: P,
+    tensor: &MnistTensor,
+    selection: &Selection,
+) -> io::Result<()> {
+    let (images, labels) = to_idx(&selection.apply(tensor))?;
+    idx::write_file(images_path, &images)?;
+    idx::write_file(labels_path, &labels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::{arr1, Array2};
+    use std::io::Read;
+
+    fn tensor() -> MnistTensor {
+        MnistTensor {
+            images: Array2::from_shape_fn((4, 4), |(i, j)| (i * 4 + j) as f64),
+            labels: arr1(&[3, 1, 3, 0]),
+            rows: 2,
+            cols: 2,
+        }
+    }
+
+    #[test]
+    fn test_selection() {
+        let t = tensor();
+        assert_eq!(Selection::new().indices(&t), vec![0, 1, 2, 3]);
+        assert_eq!(Selection::new().classes(&[3]).indices(&t), vec![0, 2]);
+        assert_eq!(
+            Selection::new().range(1..10).classes(&[3, 0]).indices(&t),
+            vec![2, 3]
+        );
+        let selected = Selection::new().range(1..3).apply(&t);
+        assert_eq!(selected.images.row(0).to_vec(), vec![4., 5., 6., 7.]);
+        assert_eq!(selected.labels, arr1(&[1, 3]));
+    }
+
+    #[test]
+    fn test_csv() {
+        let mut t = Selection::new().range(0..2).apply(&tensor());
+        t.images[[1, 0]] = 0.1;
+        let mut out = vec![];
+        write_csv(&mut out, &t).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "label,pixel0,pixel1,pixel2,pixel3\n3,0,1,2,3\n1,0.1,5,6,7\n"
+        );
+    }
+
+    #[test]
+    fn test_npz() {
+        let mut out = io::Cursor::new(vec![]);
+        write_npz(&mut out, &tensor()).unwrap();
+        let mut zip = zip::ZipArchive::new(out).unwrap();
+        let mut labels = vec![];
+        zip.by_name("labels.npy")
+            .unwrap()
+            .read_to_end(&mut labels)
+            .unwrap();
+        assert_eq!(&labels[..8], b"\x93NUMPY\x01\x00");
+        let header_len = u16::from_le_bytes([labels[8], labels[9]]) as usize;
+        assert_eq!((10 + header_len) % 64, 0);
+        let header = String::from_utf8(labels[10..10 + header_len].to_vec()).unwrap();
+        assert!(header.contains("'shape': (4,)"));
+        assert_eq!(&labels[10 + header_len..], &[3, 1, 3, 0]);
+
+        let images = zip.by_name("images.npy").unwrap();
+        assert_eq!(images.size() as usize, 128 + 16 * 8);
+    }
+
+    #[test]
+    fn test_idx() {
+        let dir = tempfile::tempdir().unwrap();
+        let (images, labels) = (dir.path().join("i.gz"), dir.path().join("l"));
+        export_idx(&images, &labels, &tensor(), &Selection::new().classes(&[3])).unwrap();
+        let images = idx::read_file(&images).unwrap();
+        assert_eq!(images.shape(), &[2, 2, 2]);
+        assert_eq!(images.into_u8().unwrap()[[1, 0, 1]], 9);
+        assert_eq!(
+            idx::read_file(&labels).unwrap().into_u8().unwrap(),
+            arr1(&[3, 3]).into_dyn()
+        );
+
+        let mut t = tensor();
+        t.images[[0, 0]] = 0.5;
+        assert_eq!(to_idx(&t).unwrap().0.data_type(), idx::IdxType::Double);
+    }
+}
@@ -0,0 +1,441 @@
+use super::super::utils::fetch_client::{archive, ArchiveKind};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use libflate::gzip;
+use ndarray::{ArrayBase, ArrayD, Data, Dimension, IxDyn};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// The type of the elements of an IDX file, stored in the third byte of the magic number.
+/// See also: FILE FORMATS FOR THE MNIST DATABASE of <http://yann.lecun.com/exdb/mnist/>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdxType {
+    /// unsigned byte
+    UByte = 0x08,
+    /// signed byte
+    SByte = 0x09,
+    /// short (2 bytes)
+    Short = 0x0B,
+    /// int (4 bytes)
+    Int = 0x0C,
+    /// float (4 bytes)
+    Float = 0x0D,
+    /// double (8 bytes)
+    Double = 0x0E,
+}
+
+impl IdxType {
+    /// `from_byte` converts the type byte of the magic number
+    pub fn from_byte(b: u8) -> io::Result<Self> {
+        match b {
+            0x08 => Ok(IdxType::UByte),
+            0x09 => Ok(IdxType::SByte),
+            0x0B => Ok(IdxType::Short),
+            0x0C => Ok(IdxType::Int),
+            0x0D => Ok(IdxType::Float),
+            0x0E => Ok(IdxType::Double),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown IDX data type: {:#04x}", b),
+            )),
+        }
+    }
+
+    /// `size` returns the number of bytes of an element
+    pub fn size(self) -> usize {
+        match self {
+            IdxType::UByte | IdxType::SByte => 1,
+            IdxType::Short => 2,
+            IdxType::Int | IdxType::Float => 4,
+            IdxType::Double => 8,
+        }
+    }
+}
+
+/// The header of an IDX file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdxHeader {
+    /// The type of the elements
+    pub data_type: IdxType,
+    /// The size of each dimension
+    pub shape: Vec<usize>,
+}
+
+impl IdxHeader {
+    /// `len` returns the number of elements
+    pub fn len(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    /// `is_empty` checks if the file has no elements
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// `data_len` returns the size of the elements in bytes,
+    /// or `None` if it overflows (e.g. the header of a corrupt file)
+    pub fn data_len(&self) -> Option<usize> {
+        self.shape
+            .iter()
+            .try_fold(self.data_type.size(), |acc, &x| acc.checked_mul(x))
+    }
+
+    /// `byte_len` returns the size of the header in bytes,
+    /// i.e. the offset of the first element in the file
+    pub fn byte_len(&self) -> usize {
+        4 + 4 * self.shape.len()
+    }
+}
+
+/// `read_header` reads the magic number and the dimensions of an IDX file
+pub fn read_header<R: Read>(r: &mut R) -> io::Result<IdxHeader> {
+    let mut magic = [0u8; 4];
+    r.read_exact(&mut magic)?;
+    if magic[0] != 0 || magic[1] != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the magic number of IDX file must start with two zero bytes",
+        ));
+    }
+    let data_type = IdxType::from_byte(magic[2])?;
+    let mut shape = Vec::with_capacity(magic[3] as usize);
+    for _ in 0..magic[3] {
+        shape.push(r.read_u32::<BigEndian>()? as usize);
+    }
+    Ok(IdxHeader { data_type, shape })
+}
+
+/// `IdxElement` is the type that can be an element of an IDX file
+pub trait IdxElement: Copy {
+    /// The type byte of the element
+    const DATA_TYPE: IdxType;
+
+    /// `read_vec` reads `n` elements.
+    /// It returns `Err` without allocating `n` elements up front if the data ends before them.
+    fn read_vec<R: Read>(r: &mut R, n: usize) -> io::Result<Vec<Self>>;
+
+    /// `write_to` writes the element in big endian
+    fn write_to<W: Write>(self, w: &mut W) -> io::Result<()>;
+}
+
+impl IdxElement for u8 {
+    const DATA_TYPE: IdxType = IdxType::UByte;
+
+    fn read_vec<R: Read>(r: &mut R, n: usize) -> io::Result<Vec<Self>> {
+        read_bytes(r, n, 1)
+    }
+
+    fn write_to<W: Write>(self, w: &mut W) -> io::Result<()> {
+        w.write_u8(self)
+    }
+}
+
+impl IdxElement for i8 {
+    const DATA_TYPE: IdxType = IdxType::SByte;
+
+    fn read_vec<R: Read>(r: &mut R, n: usize) -> io::Result<Vec<Self>> {
+        Ok(read_bytes(r, n, 1)?.into_iter().map(|x| x as i8).collect())
+    }
+
+    fn write_to<W: Write>(self, w: &mut W) -> io::Result<()> {
+        w.write_i8(self)
+    }
+}
+
+/// `read_bytes` reads the `n` elements of `size` bytes.
+/// The buffer grows as the data arrives, so the size given by the header of a corrupt or
+/// truncated file is never allocated before the data is actually read.
+fn read_bytes<R: Read>(r: &mut R, n: usize, size: usize) -> io::Result<Vec<u8>> {
+    let len = n
+        .checked_mul(size)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "IDX file is too large"))?;
+    let mut buf = Vec::new();
+    r.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!(
+                "IDX file is truncated: {} bytes of data are expected, but {} are found",
+                len,
+                buf.len()
+            ),
+        ));
+    }
+    Ok(buf)
+}
+
+macro_rules! impl_idx_element {
+    ($t:ty, $data_type:expr, $read:ident, $write:ident) => {
+        impl IdxElement for $t {
+            const DATA_TYPE: IdxType = $data_type;
+
+            fn read_vec<R: Read>(r: &mut R, n: usize) -> io::Result<Vec<Self>> {
+                let bytes = read_bytes(r, n, std::mem::size_of::<$t>())?;
+                let mut v = vec![Default::default(); n];
+                bytes.as_slice().$read::<BigEndian>(&mut v)?;
+                Ok(v)
+            }
+
+            fn write_to<W: Write>(self, w: &mut W) -> io::Result<()> {
+                w.$write::<BigEndian>(self)
+            }
+        }
+    };
+}
+
+impl_idx_element!(i16, IdxType::Short, read_i16_into, write_i16);
+impl_idx_element!(i32, IdxType::Int, read_i32_into, write_i32);
+impl_idx_element!(f32, IdxType::Float, read_f32_into, write_f32);
+impl_idx_element!(f64, IdxType::Double, read_f64_into, write_f64);
+
+/// The content of an IDX file. The variant is decided by the type byte of the file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdxData {
+    /// unsigned byte
+    UByte(ArrayD<u8>),
+    /// signed byte
+    SByte(ArrayD<i8>),
+    /// short
+    Short(ArrayD<i16>),
+    /// int
+    Int(ArrayD<i32>),
+    /// float
+    Float(ArrayD<f32>),
+    /// double
+    Double(ArrayD<f64>),
+}
+
+impl IdxData {
+    /// `data_type` returns the type of the elements
+    pub fn data_type(&self) -> IdxType {
+        match self {
+            IdxData::UByte(_) => IdxType::UByte,
+            IdxData::SByte(_) => IdxType::SByte,
+            IdxData::Short(_) => IdxType::Short,
+            IdxData::Int(_) => IdxType::Int,
+            IdxData::Float(_) => IdxType::Float,
+            IdxData::Double(_) => IdxType::Double,
+        }
+    }
+
+    /// `shape` returns the size of each dimension
+    pub fn shape(&self) -> &[usize] {
+        match self {
+            IdxData::UByte(a) => a.shape(),
+            IdxData::SByte(a) => a.shape(),
+            IdxData::Short(a) => a.shape(),
+            IdxData::Int(a) => a.shape(),
+            IdxData::Float(a) => a.shape(),
+            IdxData::Double(a) => a.shape(),
+        }
+    }
+
+    /// `to_f64` converts the elements of any type to `f64`
+    pub fn to_f64(&self) -> ArrayD<f64> {
+        match self {
+            IdxData::UByte(a) => a.mapv(f64::from),
+            IdxData::SByte(a) => a.mapv(f64::from),
+            IdxData::Short(a) => a.mapv(f64::from),
+            IdxData::Int(a) => a.mapv(f64::from),
+            IdxData::Float(a) => a.mapv(f64::from),
+            IdxData::Double(a) => a.clone(),
+        }
+    }
+
+    /// `into_u8` returns the elements if the type is unsigned byte
+    pub fn into_u8(self) -> io::Result<ArrayD<u8>> {
+        match self {
+            IdxData::UByte(a) => Ok(a),
+            other => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "expected IDX data of unsigned byte, but found {:?}",
+                    other.data_type()
+                ),
+            )),
+        }
+    }
+
+    /// `write` writes the data in IDX format
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match self {
+            IdxData::UByte(a) => write(w, a),
+            IdxData::SByte(a) => write(w, a),
+            IdxData::Short(a) => write(w, a),
+            IdxData::Int(a) => write(w, a),
+            IdxData::Float(a) => write(w, a),
+            IdxData::Double(a) => write(w, a),
+        }
+    }
+}
+
+fn read_array<T: IdxElement, R: Read>(r: &mut R, shape: &[usize]) -> io::Result<ArrayD<T>> {
+    let n = shape.iter().try_fold(1usize, |acc, &x| acc.checked_mul(x));
+    let n = n.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "IDX file is too large"))?;
+    let v = T::read_vec(r, n)?;
+    ArrayD::from_shape_vec(IxDyn(shape), v)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+}
+
+/// `read` reads an IDX file of any element type and any number of dimensions
+pub fn read<R: Read>(r: &mut R) -> io::Result<IdxData> {
+    let header = read_header(r)?;
+    read_data(r, &header)
+}
+
+fn read_data<R: Read>(r: &mut R, header: &IdxHeader) -> io::Result<IdxData> {
+    let shape = header.shape.as_slice();
+    Ok(match header.data_type {
+        IdxType::UByte => IdxData::UByte(read_array(r, shape)?),
+        IdxType::SByte => IdxData::SByte(read_array(r, shape)?),
+        IdxType::Short => IdxData::Short(read_array(r, shape)?),
+        IdxType::Int => IdxData::Int(read_array(r, shape)?),
+        IdxType::Float => IdxData::Float(read_array(r, shape)?),
+        IdxType::Double => IdxData::Double(read_array(r, shape)?),
+    })
+}
+
+/// `read_file` reads the IDX file at `path`.
+/// The file compressed by gzip, bzip2 or xz (e.g. `train-images-idx3-ubyte.gz`) is decompressed on the fly.
+/// The size of the uncompressed file is checked against its header before the elements are read.
+pub fn read_file<P: AsRef<Path>>(path: P) -> io::Result<IdxData> {
+    let path = path.as_ref();
+    match ArchiveKind::detect(path)? {
+        Some(kind) if kind.is_single_file() => read(&mut archive::open_decoder(path, kind)?),
+        _ => {
+            let file = File::open(path)?;
+            let file_len = file.metadata()?.len();
+            let mut r = BufReader::new(file);
+            let header = read_header(&mut r)?;
+            let expected = header
+                .data_len()
+                .and_then(|len| (header.byte_len() as u64).checked_add(len as u64));
+            if expected != Some(file_len) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "{}: the size of the file does not match the header {:?}",
+                        path.display(),
+                        header.shape
+                    ),
+                ));
+            }
+            read_data(&mut r, &header)
+        }
+    }
+}
+
+/// `write` writes `array` in IDX format.
+/// The type byte is decided by the element type.
+///
+/// # Arguments
+///
+/// * `w` - Destination
+/// * `array` - Array of any number of dimensions up to 255, whose every dimension fits in 32 bits
+pub fn write<A, S, D, W>(w: &mut W, array: &ArrayBase<S, D>) -> io::Result<()>
+where
+    A: IdxElement,
+    S: Data<Elem = A>,
+    D: Dimension,
+    W: Write,
+{
+    let shape = array.shape();
+    if shape.len() > u8::MAX as usize || shape.iter().any(|&x| x > u32::MAX as usize) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("the shape {:?} cannot be written in IDX format", shape),
+        ));
+    }
+    w.write_all(&[0, 0, A::DATA_TYPE as u8, shape.len() as u8])?;
+    for &x in shape {
+        w.write_u32::<BigEndian>(x as u32)?;
+    }
+    for &x in array.iter() {
+        x.write_to(w)?;
+    }
+    Ok(())
+}
+
+/// `write_file` writes `data` to the file at `path` in IDX format.
+/// The file is compressed by gzip if the extension is `.gz`.
+pub fn write_file<P: AsRef<Path>>(path: P, data: &IdxData) -> io::Result<()> {
+    let path = path.as_ref();
+    let out = BufWriter::new(File::create(path)?);
+    if ArchiveKind::from_file_name(&path.to_string_lossy()) == Some(ArchiveKind::Gzip) {
+        let mut encoder = gzip::Encoder::new(out)?;
+        data.write(&mut encoder)?;
+        encoder.finish().into_result()?.flush()
+    } else {
+        let mut out = out;
+        data.write(&mut out)?;
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::{arr1, arr3};
+
+    #[test]
+    fn test_roundtrip() {
+        let data = [
+            IdxData::UByte(arr1(&[0u8, 1, 255]).into_dyn()),
+            IdxData::SByte(arr1(&[-128i8, 0, 127]).into_dyn()),
+            IdxData::Short(arr3(&[[[-2i16, 3]], [[4, -5]]]).into_dyn()),
+            IdxData::Int(arr1(&[i32::MIN, 0, i32::MAX]).into_dyn()),
+            IdxData::Float(arr1(&[1.5f32, -0.25]).into_dyn()),
+            IdxData::Double(arr3(&[[[0.1f64], [-2e100]]]).into_dyn()),
+        ];
+        for d in data.iter() {
+            let mut buf = Vec::new();
+            d.write(&mut buf).unwrap();
+            let header = read_header(&mut buf.as_slice()).unwrap();
+            assert_eq!(header.data_type, d.data_type());
+            assert_eq!(header.shape, d.shape());
+            assert_eq!(
+                buf.len(),
+                header.byte_len() + header.len() * header.data_type.size()
+            );
+            assert_eq!(read(&mut buf.as_slice()).unwrap(), *d);
+        }
+    }
+
+    #[test]
+    fn test_read_mnist_labels() {
+        // The header of MNIST label file (magic number 2049) followed by three labels
+        let buf = [0u8, 0, 8, 1, 0, 0, 0, 3, 7, 2, 1];
+        let labels = read(&mut &buf[..]).unwrap().into_u8().unwrap();
+        assert_eq!(labels, arr1(&[7u8, 2, 1]).into_dyn());
+        assert!(read(&mut &buf[..10]).is_err());
+    }
+
+    #[test]
+    fn test_read_corrupt_header() {
+        // The header claims 2^32 - 1 labels, but only three follow.
+        let buf = [0u8, 0, 8, 1, 0xff, 0xff, 0xff, 0xff, 7, 2, 1];
+        let err = read(&mut &buf[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        // The number of bytes of the doubles overflows.
+        let mut buf = vec![0u8, 0, 0x0E, 3];
+        buf.extend_from_slice(&[0xff; 12]);
+        let err = read(&mut buf.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("labels-idx1-ubyte");
+        std::fs::write(&path, [0u8, 0, 8, 1, 0, 0, 0, 4, 7, 2, 1]).unwrap();
+        let err = read_file(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_gzip_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("images-idx3-ubyte.gz");
+        let data = IdxData::UByte(arr3(&[[[1u8, 2], [3, 4]]]).into_dyn());
+        write_file(&path, &data).unwrap();
+        assert_eq!(read_file(&path).unwrap(), data);
+    }
+}
@@ -0,0 +1,492 @@
+use super::super::utils::fetch_client::{
+    ArchiveKind, ClientOptions, FConf, FetchClient, HashAlgorithm, RemoteFile,
+};
+use super::idx_mmap::{self, LazyMnist};
+use super::mnist::{self, DatasetKey, MnistImage, MnistTensor};
+use super::tensor_cache::{self, MappedTensor};
+use super::LoadOptions;
+use ndarray::{ArrayD, IxDyn};
+use std::io;
+use std::vec;
+
+/// `IdxDataset` describes an image classification dataset distributed as gzipped IDX files
+/// in the same layout as MNIST.
+/// The built-in descriptors are `MNIST`, `FASHION_MNIST`, `KMNIST` and `EMNIST_*`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use deep_learning_playground::setup::idx_dataset::FASHION_MNIST;
+/// use deep_learning_playground::setup::mnist::train_dataset;
+///
+/// let train = FASHION_MNIST.load_tensor(train_dataset(), true).unwrap();
+/// println!("{}", FASHION_MNIST.class_name(train.labels[0]).unwrap());
+/// ```
+#[derive(Debug)]
+pub struct IdxDataset {
+    /// The name of the dataset
+    pub name: &'static str,
+    /// The name of the directory of the dataset
+    pub save_dir: &'static str,
+    /// The files to be fetched
+    pub files: &'static [RemoteFile<'static>],
+    /// The path of the training set images relative to the directory
+    pub train_images: &'static str,
+    /// The path of the training set labels relative to the directory
+    pub train_labels: &'static str,
+    /// The path of the test set images relative to the directory
+    pub test_images: &'static str,
+    /// The path of the test set labels relative to the directory
+    pub test_labels: &'static str,
+    /// The name of each class indexed by the label
+    pub class_names: &'static [&'static str],
+    /// Flag that determines whether the rows and columns of the images are swapped
+    /// (EMNIST stores the images transposed)
+    pub transpose: bool,
+}
+
+impl IdxDataset {
+    /// `fetch_client` constructs the `FetchClient` of the files of the dataset
+    pub fn fetch_client(&self, opts: &ClientOptions) -> io::Result<FetchClient<'static>> {
+        FetchClient::with_options(FConf::new(self.save_dir, self.files.iter()), opts)
+    }
+
+    /// `class_name` returns the name of the class of `label`
+    pub fn class_name(&self, label: u8) -> Option<&'static str> {
+        self.class_names.get(label as usize).cloned()
+    }
+
+    /// `load_data` downloads the dataset if it is not saved yet, and decodes it.
+    ///
+    /// # Arguments
+    ///
+    /// * `dataset_key` - `mnist::train_dataset()` or `mnist::test_dataset()`.
+    /// * `normalize` - Flag that determines whether the image is normalized between 0.0 and 1.0.
+    pub fn load_data(
+        &self,
+        dataset_key: DatasetKey,
+        normalize: bool,
+    ) -> io::Result<vec::Vec<MnistImage>> {
+        self.load_data_with(dataset_key, normalize, &LoadOptions::default())
+    }
+
+    /// `load_data_with` is the same as `load_data`
+    /// except that the download and decoding are set up by `opts`
+    pub fn load_data_with(
+        &self,
+        dataset_key: DatasetKey,
+        normalize: bool,
+        opts: &LoadOptions,
+    ) -> io::Result<vec::Vec<MnistImage>> {
+        Ok(self
+            .load_tensor_with(dataset_key, normalize, opts)?
+            .to_images())
+    }
+
+    /// `load_tensor` is the same as `load_data`
+    /// except that it returns the whole dataset as one contiguous `MnistTensor`.
+    pub fn load_tensor(&self, dataset_key: DatasetKey, normalize: bool) -> io::Result<MnistTensor> {
+        self.load_tensor_with(dataset_key, normalize, &LoadOptions::default())
+    }
+
+    /// `load_tensor_with` is the same as `load_tensor`
+    /// except that the download and decoding are set up by `opts`
+    pub fn load_tensor_with(
+        &self,
+        dataset_key: DatasetKey,
+        normalize: bool,
+        opts: &LoadOptions,
+    ) -> io::Result<MnistTensor> {
+        let client = self.fetch_client(&opts.client)?;
+        client.get()?;
+
+        let (img_fname, label_fname) = self.file_names(&dataset_key);
+        let build = || self.decode(&client, img_fname, label_fname);
+        if !opts.decoded_cache {
+            let (images, labels) = build()?;
+            return MnistTensor::from_idx(images, labels, normalize);
+        }
+        let settings = format!("transpose={}", self.transpose);
+        tensor_cache::load_or_build(
+            &client,
+            &[img_fname, label_fname],
+            &settings,
+            normalize,
+            build,
+        )
+    }
+
+    /// `load_mapped` is the same as `mnist::load_mapped` for this dataset
+    pub fn load_mapped(
+        &self,
+        dataset_key: DatasetKey,
+        normalize: bool,
+    ) -> io::Result<MappedTensor> {
+        self.load_mapped_with(dataset_key, normalize, &ClientOptions::default())
+    }
+
+    /// `load_mapped_with` is the same as `load_mapped`
+    /// except that the download and decoding are set up by `opts`
+    pub fn load_mapped_with(
+        &self,
+        dataset_key: DatasetKey,
+        normalize: bool,
+        opts: &ClientOptions,
+    ) -> io::Result<MappedTensor> {
+        let client = self.fetch_client(opts)?;
+        client.get()?;
+        let (img_fname, label_fname) = self.file_names(&dataset_key);
+        let settings = format!("transpose={}", self.transpose);
+        let mapped = tensor_cache::load_mapped_or_build(
+            &client,
+            &[img_fname, label_fname],
+            &settings,
+            || self.decode(&client, img_fname, label_fname),
+        )?;
+        Ok(mapped.normalize(normalize))
+    }
+
+    fn decode(
+        &self,
+        client: &FetchClient<'static>,
+        img_fname: &'static str,
+        label_fname: &'static str,
+    ) -> io::Result<(ArrayD<u8>, ArrayD<u8>)> {
+        let (images, labels) = mnist::decode(client.clone(), img_fname, label_fname)?;
+        if self.transpose {
+            Ok((transpose_images(images), labels))
+        } else {
+            Ok((images, labels))
+        }
+    }
+
+    /// `load_lazy` is the same as `mnist::load_lazy` for this dataset
+    pub fn load_lazy(&self, dataset_key: DatasetKey, normalize: bool) -> io::Result<LazyMnist> {
+        self.load_lazy_with(dataset_key, normalize, &ClientOptions::default())
+    }
+
+    /// `load_lazy_with` is the same as `load_lazy`
+    /// except that the download is set up by `opts`
+    pub fn load_lazy_with(
+        &self,
+        dataset_key: DatasetKey,
+        normalize: bool,
+        opts: &ClientOptions,
+    ) -> io::Result<LazyMnist> {
+        let client = self.fetch_client(opts)?;
+        client.get()?;
+        let (img_fname, label_fname) = self.file_names(&dataset_key);
+        Ok(
+            idx_mmap::open_lazy(&client, img_fname, label_fname, normalize)?
+                .transpose(self.transpose),
+        )
+    }
+
+    fn file_names(&self, dataset_key: &DatasetKey) -> (&'static str, &'static str) {
+        if dataset_key.is_train() {
+            (self.train_images, self.train_labels)
+        } else {
+            (self.test_images, self.test_labels)
+        }
+    }
+}
+
+fn transpose_images(images: ArrayD<u8>) -> ArrayD<u8> {
+    if images.ndim() != 3 {
+        return images;
+    }
+    let transposed = images.permuted_axes(IxDyn(&[0, 2, 1]));
+    let shape = transposed.shape().to_vec();
+    ArrayD::from_shape_vec(IxDyn(&shape), transposed.iter().cloned().collect()).unwrap()
+}
+
+const DIGITS: [&str; 10] = ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"];
+
+/// MNIST (<http://yann.lecun.com/exdb/mnist/>).
+/// It is decoded in the same way as `mnist::load_data`.
+pub static MNIST: IdxDataset = IdxDataset {
+    name: "mnist",
+    save_dir: mnist::MNIST_SAVE_DIR,
+    files: &mnist::FILES,
+    train_images: "train-images-idx3-ubyte.gz",
+    train_labels: "train-labels-idx1-ubyte.gz",
+    test_images: "t10k-images-idx3-ubyte.gz",
+    test_labels: "t10k-labels-idx1-ubyte.gz",
+    class_names: &DIGITS,
+    transpose: false,
+};
+
+const FASHION_MNIST_URL_BASE: &str = "http://fashion-mnist.s3-website.eu-central-1.amazonaws.com/";
+
+static FASHION_MNIST_FILES: [RemoteFile; 4] = [
+    RemoteFile {
+        host_and_path: FASHION_MNIST_URL_BASE,
+        fname: "train-images-idx3-ubyte.gz",
+        digest: "8d4fb7e6c68d591d4c3dfef9ec88bf0d",
+        algorithm: HashAlgorithm::Md5,
+        query: "",
+        archive: None,
+    },
+    RemoteFile {
+        host_and_path: FASHION_MNIST_URL_BASE,
+        fname: "train-labels-idx1-ubyte.gz",
+        digest: "25c81989df183df01b3e8a0aad5dffbe",
+        algorithm: HashAlgorithm::Md5,
+        query: "",
+        archive: None,
+    },
+    RemoteFile {
+        host_and_path: FASHION_MNIST_URL_BASE,
+        fname: "t10k-images-idx3-ubyte.gz",
+        digest: "bef4ecab320f06d8554ea6380940ec79",
+        algorithm: HashAlgorithm::Md5,
+        query: "",
+        archive: None,
+    },
+    RemoteFile {
+        host_and_path: FASHION_MNIST_URL_BASE,
+        fname: "t10k-labels-idx1-ubyte.gz",
+        digest: "bb300cfdad3c16e7a12a480ee83cd310",
+        algorithm: HashAlgorithm::Md5,
+        query: "",
+        archive: None,
+    },
+];
+
+/// Fashion-MNIST (<https://github.com/zalandoresearch/fashion-mnist>)
+pub static FASHION_MNIST: IdxDataset = IdxDataset {
+    name: "fashion-mnist",
+    save_dir: ".fashion_mnist",
+    files: &FASHION_MNIST_FILES,
+    train_images: "train-images-idx3-ubyte.gz",
+    train_labels: "train-labels-idx1-ubyte.gz",
+    test_images: "t10k-images-idx3-ubyte.gz",
+    test_labels: "t10k-labels-idx1-ubyte.gz",
+    class_names: &[
+        "T-shirt/top",
+        "Trouser",
+        "Pullover",
+        "Dress",
+        "Coat",
+        "Sandal",
+        "Shirt",
+        "Sneaker",
+        "Bag",
+        "Ankle boot",
+    ],
+    transpose: false,
+};
+
+const KMNIST_URL_BASE: &str = "http://codh.rois.ac.jp/kmnist/dataset/kmnist/";
+
+static KMNIST_FILES: [RemoteFile; 4] = [
+    RemoteFile {
+        host_and_path: KMNIST_URL_BASE,
+        fname: "train-images-idx3-ubyte.gz",
+        digest: "bdb82020997e1d708af4cf47b453dcf7",
+        algorithm: HashAlgorithm::Md5,
+        query: "",
+        archive: None,
+    },
+    RemoteFile {
+        host_and_path: KMNIST_URL_BASE,
+        fname: "train-labels-idx1-ubyte.gz",
+        digest: "e144d726b3acfaa3e44228e80efcd344",
+        algorithm: HashAlgorithm::Md5,
+        query: "",
+        archive: None,
+    },
+    RemoteFile {
+        host_and_path: KMNIST_URL_BASE,
+        fname: "t10k-images-idx3-ubyte.gz",
+        digest: "5c965bf0a639b31b8f53240b1b52f4d7",
+        algorithm: HashAlgorithm::Md5,
+        query: "",
+        archive: None,
+    },
+    RemoteFile {
+        host_and_path: KMNIST_URL_BASE,
+        fname: "t10k-labels-idx1-ubyte.gz",
+        digest: "7320c461ea6c1c855c0b718fb2a4b134",
+        algorithm: HashAlgorithm::Md5,
+        query: "",
+        archive: None,
+    },
+];
+
+/// Kuzushiji-MNIST (<https://github.com/rois-codh/kmnist>).
+/// The class names are the romanized hiragana.
+pub static KMNIST: IdxDataset = IdxDataset {
+    name: "kmnist",
+    save_dir: ".kmnist",
+    files: &KMNIST_FILES,
+    train_images: "train-images-idx3-ubyte.gz",
+    train_labels: "train-labels-idx1-ubyte.gz",
+    test_images: "t10k-images-idx3-ubyte.gz",
+    test_labels: "t10k-labels-idx1-ubyte.gz",
+    class_names: &["o", "ki", "su", "tsu", "na", "ha", "ma", "ya", "re", "wo"],
+    transpose: false,
+};
+
+// All the splits of EMNIST are distributed in one zip archive.
+static EMNIST_FILES: [RemoteFile; 1] = [RemoteFile {
+    host_and_path: "https://biometrics.nist.gov/cs_links/EMNIST/",
+    fname: "gzip.zip",
+    digest: "58c8d27c78d21e728a6bc7b3cc06412e",
+    algorithm: HashAlgorithm::Md5,
+    query: "",
+    archive: Some(ArchiveKind::Zip),
+}];
+
+const EMNIST_SAVE_DIR: &str = ".emnist";
+
+const EMNIST_BALANCED_CLASSES: [&str; 47] = [
+    "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "A", "B", "C", "D", "E", "F", "G", "H", "I",
+    "J", "K", "L", "M", "N", "O", "P", "Q", "R", "S", "T", "U", "V", "W", "X", "Y", "Z", "a", "b",
+    "d", "e", "f", "g", "h", "n", "q", "r", "t",
+];
+
+const EMNIST_BYCLASS_CLASSES: [&str; 62] = [
+    "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "A", "B", "C", "D", "E", "F", "G", "H", "I",
+    "J", "K", "L", "M", "N", "O", "P", "Q", "R", "S", "T", "U", "V", "W", "X", "Y", "Z", "a", "b",
+    "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m", "n", "o", "p", "q", "r", "s", "t", "u",
+    "v", "w", "x", "y", "z",
+];
+
+// The labels of the letters split start from 1.
+const EMNIST_LETTERS_CLASSES: [&str; 27] = [
+    "N/A", "a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m", "n", "o", "p", "q",
+    "r", "s", "t", "u", "v", "w", "x", "y", "z",
+];
+
+/// EMNIST Balanced (<https://www.nist.gov/itl/products-and-services/emnist-dataset>)
+pub static EMNIST_BALANCED: IdxDataset = IdxDataset {
+    name: "emnist-balanced",
+    save_dir: EMNIST_SAVE_DIR,
+    files: &EMNIST_FILES,
+    train_images: "gzip/emnist-balanced-train-images-idx3-ubyte.gz",
+    train_labels: "gzip/emnist-balanced-train-labels-idx1-ubyte.gz",
+    test_images: "gzip/emnist-balanced-test-images-idx3-ubyte.gz",
+    test_labels: "gzip/emnist-balanced-test-labels-idx1-ubyte.gz",
+    class_names: &EMNIST_BALANCED_CLASSES,
+    transpose: true,
+};
+
+/// EMNIST ByClass
+pub static EMNIST_BYCLASS: IdxDataset = IdxDataset {
+    name: "emnist-byclass",
+    save_dir: EMNIST_SAVE_DIR,
+    files: &EMNIST_FILES,
+    train_images: "gzip/emnist-byclass-train-images-idx3-ubyte.gz",
+    train_labels: "gzip/emnist-byclass-train-labels-idx1-ubyte.gz",
+    test_images: "gzip/emnist-byclass-test-images-idx3-ubyte.gz",
+    test_labels: "gzip/emnist-byclass-test-labels-idx1-ubyte.gz",
+    class_names: &EMNIST_BYCLASS_CLASSES,
+    transpose: true,
+};
+
+/// EMNIST ByMerge, which merges the uppercase and lowercase letters of similar shapes
+/// in the same way as EMNIST Balanced
+pub static EMNIST_BYMERGE: IdxDataset = IdxDataset {
+    name: "emnist-bymerge",
+    save_dir: EMNIST_SAVE_DIR,
+    files: &EMNIST_FILES,
+    train_images: "gzip/emnist-bymerge-train-images-idx3-ubyte.gz",
+    train_labels: "gzip/emnist-bymerge-train-labels-idx1-ubyte.gz",
+    test_images: "gzip/emnist-bymerge-test-images-idx3-ubyte.gz",
+    test_labels: "gzip/emnist-bymerge-test-labels-idx1-ubyte.gz",
+    class_names: &EMNIST_BALANCED_CLASSES,
+    transpose: true,
+};
+
+/// EMNIST Digits
+pub static EMNIST_DIGITS: IdxDataset = IdxDataset {
+    name: "emnist-digits",
+    save_dir: EMNIST_SAVE_DIR,
+    files: &EMNIST_FILES,
+    train_images: "gzip/emnist-digits-train-images-idx3-ubyte.gz",
+    train_labels: "gzip/emnist-digits-train-labels-idx1-ubyte.gz",
+    test_images: "gzip/emnist-digits-test-images-idx3-ubyte.gz",
+    test_labels: "gzip/emnist-digits-test-labels-idx1-ubyte.gz",
+    class_names: &DIGITS,
+    transpose: true,
+};
+
+/// EMNIST Letters, whose labels are from 1 (`a`) to 26 (`z`) merging the uppercase and lowercase
+pub static EMNIST_LETTERS: IdxDataset = IdxDataset {
+    name: "emnist-letters",
+    save_dir: EMNIST_SAVE_DIR,
+    files: &EMNIST_FILES,
+    train_images: "gzip/emnist-letters-train-images-idx3-ubyte.gz",
+    train_labels: "gzip/emnist-letters-train-labels-idx1-ubyte.gz",
+    test_images: "gzip/emnist-letters-test-images-idx3-ubyte.gz",
+    test_labels: "gzip/emnist-letters-test-labels-idx1-ubyte.gz",
+    class_names: &EMNIST_LETTERS_CLASSES,
+    transpose: true,
+};
+
+/// EMNIST MNIST, the digits in the same size as MNIST
+pub static EMNIST_MNIST: IdxDataset = IdxDataset {
+    name: "emnist-mnist",
+    save_dir: EMNIST_SAVE_DIR,
+    files: &EMNIST_FILES,
+    train_images: "gzip/emnist-mnist-train-images-idx3-ubyte.gz",
+    train_labels: "gzip/emnist-mnist-train-labels-idx1-ubyte.gz",
+    test_images: "gzip/emnist-mnist-test-images-idx3-ubyte.gz",
+    test_labels: "gzip/emnist-mnist-test-labels-idx1-ubyte.gz",
+    class_names: &DIGITS,
+    transpose: true,
+};
+
+/// All the built-in descriptors
+pub static DATASETS: [&IdxDataset; 9] = [
+    &MNIST,
+    &FASHION_MNIST,
+    &KMNIST,
+    &EMNIST_BALANCED,
+    &EMNIST_BYCLASS,
+    &EMNIST_BYMERGE,
+    &EMNIST_DIGITS,
+    &EMNIST_LETTERS,
+    &EMNIST_MNIST,
+];
+
+/// `by_name` returns the built-in descriptor named `name`.
+/// `emnist` is accepted as the name of `EMNIST_BALANCED`,
+/// which is the split recommended by the authors of EMNIST.
+pub fn by_name(name: &str) -> Option<&'static IdxDataset> {
+    match name {
+        "emnist" => Some(&EMNIST_BALANCED),
+        _ => DATASETS.iter().find(|d| d.name == name).cloned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transpose_images() {
+        let images = ArrayD::from_shape_vec(IxDyn(&[1, 2, 3]), vec![1, 2, 3, 4, 5, 6]).unwrap();
+        let transposed = transpose_images(images);
+        assert_eq!(transposed.shape(), &[1, 3, 2]);
+        assert_eq!(transposed.into_raw_vec(), vec![1, 4, 2, 5, 3, 6]);
+    }
+
+    #[test]
+    fn test_by_name() {
+        assert_eq!(by_name("emnist-letters").unwrap().class_name(1), Some("a"));
+        assert_eq!(
+            by_name("fashion-mnist").unwrap().class_name(9),
+            Some("Ankle boot")
+        );
+        assert_eq!(by_name("emnist").unwrap().name, EMNIST_BALANCED.name);
+        assert!(by_name("cifar-10").is_none());
+        for d in DATASETS.iter() {
+            assert!(d
+                .files
+                .iter()
+                .all(|f| f.digest.len() == 32 || f.digest.len() == 64));
+        }
+    }
+}
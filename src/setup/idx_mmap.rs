@@ -0,0 +1,326 @@
+use super::super::utils::fetch_client::{archive, ArchiveKind, FetchClient};
+use super::data_loader::Dataset;
+use super::idx::{self, IdxElement, IdxHeader, IdxType};
+use super::mnist::MnistImage;
+use memmap2::Mmap;
+use ndarray::{Array2, ArrayD, ArrayViewD, IxDyn};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// `raw_path` returns the path of the file decompressed from the single file archive `path`
+/// (e.g. `train-images-idx3-ubyte` for `train-images-idx3-ubyte.gz`).
+/// It returns `path` itself if it is not a single file archive.
+pub fn raw_path(path: &Path) -> io::Result<PathBuf> {
+    match path.to_str().and_then(ArchiveKind::from_file_name) {
+        Some(kind) if kind.is_single_file() => {
+            Ok(path.with_file_name(archive::decompressed_name(path)?))
+        }
+        _ => Ok(path.to_path_buf()),
+    }
+}
+
+/// `decompress_once` decompresses the single file archive `path` next to it
+/// unless it has been decompressed already, and returns the path of the decompressed file.
+/// The archive itself may have been removed once it is decompressed.
+pub fn decompress_once(path: &Path) -> io::Result<PathBuf> {
+    let dst = raw_path(path)?;
+    if dst == path || dst.exists() {
+        return Ok(dst);
+    }
+    let kind = ArchiveKind::detect(path)?.unwrap_or(ArchiveKind::Gzip);
+    let mut part = dst.clone().into_os_string();
+    part.push(".part");
+    let part = PathBuf::from(part);
+    {
+        let mut out = io::BufWriter::new(fs::File::create(&part)?);
+        io::copy(&mut archive::open_decoder(path, kind)?, &mut out)?;
+    }
+    fs::rename(&part, &dst)?;
+    Ok(dst)
+}
+
+/// `MappedIdx` is the uncompressed IDX file mapped to memory.
+/// The samples along the first dimension are read on demand,
+/// so the whole file is never loaded into memory.
+pub struct MappedIdx {
+    mmap: Mmap,
+    header: IdxHeader,
+}
+
+impl MappedIdx {
+    /// `open` maps the uncompressed IDX file at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = fs::File::open(path)?;
+        // The decompressed files are created by renaming and never modified in place.
+        let mmap = unsafe { Mmap::map(&file)? };
+        let header = idx::read_header(&mut &mmap[..])?;
+        let expected = header
+            .data_len()
+            .and_then(|len| len.checked_add(header.byte_len()));
+        if header.shape.is_empty() || expected != Some(mmap.len()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{}: the size of the file does not match the header {:?}",
+                    path.display(),
+                    header.shape
+                ),
+            ));
+        }
+        Ok(Self { mmap, header })
+    }
+
+    /// `header` returns the header of the file
+    pub fn header(&self) -> &IdxHeader {
+        &self.header
+    }
+
+    /// `len` returns the number of samples, i.e. the size of the first dimension
+    pub fn len(&self) -> usize {
+        self.header.shape[0]
+    }
+
+    /// `is_empty` checks if there are no samples
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// `sample_shape` returns the shape of a sample, i.e. the dimensions except for the first one
+    pub fn sample_shape(&self) -> &[usize] {
+        &self.header.shape[1..]
+    }
+
+    /// `sample_bytes` returns the raw bytes of the `i`th sample in big endian.
+    /// It panics if `i` is out of range.
+    pub fn sample_bytes(&self, i: usize) -> &[u8] {
+        assert!(
+            i < self.len(),
+            "the index {} is out of {} samples",
+            i,
+            self.len()
+        );
+        let size = self.sample_shape().iter().product::<usize>() * self.header.data_type.size();
+        let start = self.header.byte_len() + i * size;
+        &self.mmap[start..start + size]
+    }
+
+    /// `view_u8` returns the view of the `i`th sample without copying.
+    /// It returns `Err` if the elements are not unsigned bytes.
+    pub fn view_u8(&self, i: usize) -> io::Result<ArrayViewD<'_, u8>> {
+        self.check_type(IdxType::UByte)?;
+        Ok(ArrayViewD::from_shape(IxDyn(self.sample_shape()), self.sample_bytes(i)).unwrap())
+    }
+
+    /// `sample` decodes the `i`th sample.
+    /// It returns `Err` if the elements are not of type `T`.
+    pub fn sample<T: IdxElement>(&self, i: usize) -> io::Result<ArrayD<T>> {
+        self.check_type(T::DATA_TYPE)?;
+        let shape = self.sample_shape();
+        let v = T::read_vec(&mut self.sample_bytes(i), shape.iter().product())?;
+        Ok(ArrayD::from_shape_vec(IxDyn(shape), v).unwrap())
+    }
+
+    fn check_type(&self, data_type: IdxType) -> io::Result<()> {
+        if self.header.data_type != data_type {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "the elements are {:?}, not {:?}",
+                    self.header.data_type, data_type
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// `LazyMnist` is the dataset in MNIST layout read lazily from the memory-mapped IDX files.
+/// Only the accessed images are decoded, so the memory usage does not depend on the size of the dataset.
+pub struct LazyMnist {
+    images: MappedIdx,
+    labels: MappedIdx,
+    normalize: bool,
+    transpose: bool,
+}
+
+impl LazyMnist {
+    /// `open` maps the uncompressed IDX files
+    ///
+    /// # Arguments
+    ///
+    /// * `images` - Path of the image file of shape (N, rows, columns)
+    /// * `labels` - Path of the label file of shape (N)
+    /// * `normalize` - Flag that determines whether the image is normalized between 0.0 and 1.0.
+    pub fn open<P: AsRef<Path>>(images: P, labels: P, normalize: bool) -> io::Result<Self> {
+        let images = MappedIdx::open(images)?;
+        let labels = MappedIdx::open(labels)?;
+        if images.header().data_type != IdxType::UByte
+            || labels.header().data_type != IdxType::UByte
+            || images.sample_shape().len() != 2
+            || labels.header().shape != [images.len()]
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "the images {:?} and labels {:?} are not in MNIST layout",
+                    images.header().shape,
+                    labels.header().shape
+                ),
+            ));
+        }
+        Ok(Self {
+            images,
+            labels,
+            normalize,
+            transpose: false,
+        })
+    }
+
+    /// `transpose` sets whether each image is transposed when it is read (e.g. EMNIST)
+    pub fn transpose(mut self, transpose: bool) -> Self {
+        self.transpose = transpose;
+        self
+    }
+
+    /// `shape` returns the rows and columns of an image
+    pub fn shape(&self) -> (usize, usize) {
+        let shape = self.images.sample_shape();
+        if self.transpose {
+            (shape[1], shape[0])
+        } else {
+            (shape[0], shape[1])
+        }
+    }
+}
+
+impl Dataset for LazyMnist {
+    type Item = MnistImage;
+
+    fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    fn get(&self, index: usize) -> Self::Item {
+        let view = self.images.view_u8(index).unwrap();
+        let view = if self.transpose {
+            view.reversed_axes()
+        } else {
+            view
+        };
+        let scale = if self.normalize { 255. } else { 1. };
+        let pixels = view.iter().map(|&x| f64::from(x) / scale).collect();
+        MnistImage {
+            image: Array2::from_shape_vec((1, view.len()), pixels).unwrap(),
+            label: self.labels.sample_bytes(index)[0],
+        }
+    }
+
+    fn label(&self, index: usize) -> Option<usize> {
+        Some(self.labels.sample_bytes(index)[0] as usize)
+    }
+}
+
+/// `open_lazy` decompresses the image file and the label file saved by `client` once
+/// and maps them as `LazyMnist`
+pub(super) fn open_lazy(
+    client: &FetchClient,
+    img_fname: &str,
+    label_fname: &str,
+    normalize: bool,
+) -> io::Result<LazyMnist> {
+    let img_path = client.dir_client.file_path(img_fname);
+    let label_path = client.dir_client.file_path(label_fname);
+    // The other processes may be decompressing the same files.
+    let _lock = match client.dir_client.lock(client.lock_timeout()) {
+        Ok(lock) => Some(lock),
+        // The read-only directory whose files are already decompressed can be used without the lock.
+        Err(ref e)
+            if e.kind() == io::ErrorKind::PermissionDenied
+                && raw_path(&img_path)?.exists()
+                && raw_path(&label_path)?.exists() =>
+        {
+            None
+        }
+        Err(e) => return Err(e),
+    };
+    let images = decompress_once(&img_path)?;
+    let labels = decompress_once(&label_path)?;
+    LazyMnist::open(images, labels, normalize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::utils::fetch_client::{CacheRoot, ClientOptions, FConf, RemoteFile};
+    use super::*;
+    use ndarray::{arr1, Array3};
+    use std::time::Duration;
+
+    #[test]
+    fn test_lazy_mnist() {
+        let dir = tempfile::tempdir().unwrap();
+        let images = Array3::from_shape_fn((3, 2, 3), |(i, r, c)| (i * 6 + r * 3 + c) as u8);
+        let img_path = dir.path().join("images-idx3-ubyte.gz");
+        let label_path = dir.path().join("labels-idx1-ubyte");
+        idx::write_file(&img_path, &idx::IdxData::UByte(images.into_dyn())).unwrap();
+        idx::write_file(
+            &label_path,
+            &idx::IdxData::UByte(arr1(&[4, 5, 6]).into_dyn()),
+        )
+        .unwrap();
+
+        let raw = decompress_once(&img_path).unwrap();
+        assert_eq!(raw, dir.path().join("images-idx3-ubyte"));
+        // The archive is no longer needed once it is decompressed.
+        fs::remove_file(&img_path).unwrap();
+        assert_eq!(decompress_once(&img_path).unwrap(), raw);
+
+        let mapped = MappedIdx::open(&raw).unwrap();
+        assert_eq!((mapped.len(), mapped.sample_shape()), (3, &[2, 3][..]));
+        assert_eq!(mapped.sample_bytes(1), &[6, 7, 8, 9, 10, 11]);
+        assert!(mapped.sample::<f32>(0).is_err());
+
+        let data = LazyMnist::open(&raw, &label_path, false).unwrap();
+        assert_eq!((data.len(), data.shape()), (3, (2, 3)));
+        let image = data.get(2);
+        assert_eq!((image.image.dim(), image.label), ((1, 6), 6));
+        assert_eq!(image.image[[0, 5]], 17.);
+        assert_eq!(data.label(0), Some(4));
+
+        let data = data.transpose(true);
+        assert_eq!(data.shape(), (3, 2));
+        assert_eq!(
+            data.get(0).image.row(0).to_vec(),
+            vec![0., 3., 1., 4., 2., 5.]
+        );
+        assert!(LazyMnist::open(&label_path, &label_path, false).is_err());
+
+        // The header claiming 2^32 - 1 elements in every dimension does not overflow.
+        let crafted = dir.path().join("crafted-idx3-ubyte");
+        let mut data = vec![0u8, 0, 0x0E, 3];
+        data.extend_from_slice(&[0xff; 12]);
+        fs::write(&crafted, &data).unwrap();
+        let err = MappedIdx::open(&crafted).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_open_lazy_waits_for_lock() {
+        let root = tempfile::tempdir().unwrap();
+        let opts = ClientOptions::new()
+            .silent()
+            .cache_root(CacheRoot::Dir(root.path().to_path_buf()))
+            .lock_timeout(Duration::from_millis(100));
+        let files: [RemoteFile; 0] = [];
+        let client =
+            FetchClient::with_options(FConf::new("fixtures", files.iter()), &opts).unwrap();
+
+        // Another process is decompressing the files.
+        let _lock = client.dir_client.lock(Duration::from_secs(1)).unwrap();
+        let err = open_lazy(&client, "images.gz", "labels.gz", false)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+}
@@ -0,0 +1,299 @@
+use super::super::utils::natural_transform::to_io;
+use super::data_loader::Dataset;
+use super::mnist::{MnistImage, MnistTensor};
+use image::imageops::FilterType;
+use ndarray::{Array1, Array2, Array4, Axis};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::vec;
+
+/// The extensions of the image files read by `ImageFolder`
+pub const EXTENSIONS: &[&str] = &["png", "jpg", "jpeg"];
+
+/// `ColorMode` decides the channels of the loaded images
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// One channel of luminance, in the same layout as MNIST
+    Gray,
+    /// Three channels of red, green and blue, in the same layout as CIFAR
+    Rgb,
+}
+
+impl Default for ColorMode {
+    fn default() -> Self {
+        ColorMode::Gray
+    }
+}
+
+impl ColorMode {
+    /// `channels` returns the number of channels
+    pub fn channels(self) -> usize {
+        match self {
+            ColorMode::Gray => 1,
+            ColorMode::Rgb => 3,
+        }
+    }
+}
+
+/// `ImageFolder` is the dataset of the image files stored in a directory per class:
+///
+/// ```text
+/// root/0/a.png
+/// root/0/b.jpg
+/// root/1/c.png
+/// ...
+/// ```
+///
+/// The classes are the names of the subdirectories in lexicographic order,
+/// and the label of an image is the index of its class.
+/// The images are resized to the same size and converted to `ColorMode`,
+/// and are laid out in the same way as `setup::mnist`:
+/// each image is a row of the pixels (channel by channel for RGB).
+///
+/// # Examples
+///
+/// ```no_run
+/// use deep_learning_playground::setup::image_folder::{ColorMode, ImageFolder};
+///
+/// let data = ImageFolder::new("digits", 28, 28).unwrap().color(ColorMode::Gray);
+/// let tensor = data.to_tensor().unwrap();
+/// println!("{} images of {:?}", tensor.len(), data.classes());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ImageFolder {
+    classes: vec::Vec<String>,
+    samples: vec::Vec<(PathBuf, u8)>,
+    rows: usize,
+    cols: usize,
+    color: ColorMode,
+    normalize: bool,
+}
+
+impl ImageFolder {
+    /// `new` collects the image files under `root`. The images are decoded on demand.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The directory which has a subdirectory per class
+    /// * `rows` - The number of rows which the images are resized to
+    /// * `cols` - The number of columns which the images are resized to
+    pub fn new<P: AsRef<Path>>(root: P, rows: usize, cols: usize) -> io::Result<Self> {
+        let root = root.as_ref();
+        let mut dirs = fs::read_dir(root)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        dirs.retain(|p| p.is_dir());
+        dirs.sort();
+        if dirs.len() > u8::MAX as usize + 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} has {} classes, but the labels are up to 256 classes",
+                    root.display(),
+                    dirs.len()
+                ),
+            ));
+        }
+
+        let mut classes = Vec::with_capacity(dirs.len());
+        let mut samples = Vec::new();
+        for (label, dir) in dirs.iter().enumerate() {
+            classes.push(dir.file_name().unwrap().to_string_lossy().into_owned());
+            let mut files = fs::read_dir(dir)?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<io::Result<Vec<_>>>()?;
+            files.retain(|p| p.is_file() && is_image(p));
+            files.sort();
+            samples.extend(files.into_iter().map(|p| (p, label as u8)));
+        }
+        Ok(Self {
+            classes,
+            samples,
+            rows,
+            cols,
+            color: ColorMode::default(),
+            normalize: true,
+        })
+    }
+
+    /// `color` sets the channels of the loaded images (grayscale by default)
+    pub fn color(mut self, color: ColorMode) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// `normalize` sets whether the pixels are normalized between 0.0 and 1.0 (true by default)
+    pub fn normalize(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    /// `classes` returns the names of the classes indexed by the labels
+    pub fn classes(&self) -> &[String] {
+        &self.classes
+    }
+
+    /// `class_name` returns the name of the class of `label`
+    pub fn class_name(&self, label: u8) -> Option<&str> {
+        self.classes.get(label as usize).map(String::as_str)
+    }
+
+    /// `samples` returns the paths of the image files and their labels
+    pub fn samples(&self) -> &[(PathBuf, u8)] {
+        &self.samples
+    }
+
+    /// `shape` returns the number of channels, rows and columns of an image
+    pub fn shape(&self) -> (usize, usize, usize) {
+        (self.color.channels(), self.rows, self.cols)
+    }
+
+    /// `load_image` decodes the `i`th image into the pixels of shape (channels * rows * columns)
+    pub fn load_image(&self, i: usize) -> io::Result<Array1<f64>> {
+        let path = &self.samples[i].0;
+        let image = image::open(path)
+            .map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {}", path.display(), e),
+                )
+            })?
+            .resize_exact(self.cols as u32, self.rows as u32, FilterType::Triangle);
+        let scale = if self.normalize { 255. } else { 1. };
+        let pixels: Vec<f64> = match self.color {
+            ColorMode::Gray => image
+                .to_luma()
+                .into_raw()
+                .into_iter()
+                .map(|x| f64::from(x) / scale)
+                .collect(),
+            ColorMode::Rgb => {
+                // Interleaved RGB to channel by channel
+                let rgb = image.to_rgb().into_raw();
+                (0..3)
+                    .flat_map(|c| rgb.iter().skip(c).step_by(3))
+                    .map(|&x| f64::from(x) / scale)
+                    .collect()
+            }
+        };
+        Ok(Array1::from(pixels))
+    }
+
+    /// `to_tensor` decodes all the grayscale images into `MnistTensor`.
+    /// It returns `Err` for `ColorMode::Rgb`; use `to_array4` instead.
+    pub fn to_tensor(&self) -> io::Result<MnistTensor> {
+        if self.color != ColorMode::Gray {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "MnistTensor holds only grayscale images; use to_array4 for RGB",
+            ));
+        }
+        Ok(MnistTensor {
+            images: self.load_all()?,
+            labels: self.samples.iter().map(|&(_, label)| label).collect(),
+            rows: self.rows,
+            cols: self.cols,
+        })
+    }
+
+    /// `to_array4` decodes all the images into the array of shape (N, channels, rows, columns)
+    /// in the same layout as `cifar::CifarImages::images`, and the labels
+    pub fn to_array4(&self) -> io::Result<(Array4<f64>, Array1<u8>)> {
+        let (channels, rows, cols) = self.shape();
+        let images = to_io(
+            self.load_all()?
+                .into_shape((self.samples.len(), channels, rows, cols)),
+            io::ErrorKind::InvalidData,
+        )?;
+        Ok((
+            images,
+            self.samples.iter().map(|&(_, label)| label).collect(),
+        ))
+    }
+
+    fn load_all(&self) -> io::Result<Array2<f64>> {
+        let (channels, rows, cols) = self.shape();
+        let mut images = Array2::zeros((self.samples.len(), channels * rows * cols));
+        for (i, mut row) in images.outer_iter_mut().enumerate() {
+            row.assign(&self.load_image(i)?);
+        }
+        Ok(images)
+    }
+}
+
+impl Dataset for ImageFolder {
+    type Item = MnistImage;
+
+    fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// It panics if the image file cannot be decoded.
+    fn get(&self, index: usize) -> Self::Item {
+        let image = self.load_image(index).unwrap_or_else(|e| panic!("{}", e));
+        MnistImage {
+            image: image.insert_axis(Axis(0)),
+            label: self.samples[index].1,
+        }
+    }
+
+    fn label(&self, index: usize) -> Option<usize> {
+        Some(self.samples[index].1 as usize)
+    }
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map_or(false, |e| EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Luma, Rgb};
+
+    #[test]
+    fn test_image_folder() {
+        let root = tempfile::tempdir().unwrap();
+        for class in ["circle", "cross"].iter() {
+            fs::create_dir(root.path().join(class)).unwrap();
+        }
+        ImageBuffer::from_fn(4, 4, |x, _| Luma([(x * 60) as u8]))
+            .save(root.path().join("cross/b.png"))
+            .unwrap();
+        ImageBuffer::from_pixel(8, 8, Rgb([255u8, 0, 51]))
+            .save(root.path().join("circle/a.png"))
+            .unwrap();
+        fs::write(root.path().join("circle/notes.txt"), "not an image").unwrap();
+
+        let data = ImageFolder::new(root.path(), 4, 4).unwrap();
+        assert_eq!(data.classes(), &["circle".to_string(), "cross".to_string()]);
+        assert_eq!(data.len(), 2);
+        assert_eq!(data.label(1), Some(1));
+
+        let tensor = data.to_tensor().unwrap();
+        assert_eq!(tensor.images.dim(), (2, 16));
+        assert_eq!(tensor.labels.to_vec(), vec![0, 1]);
+        assert_eq!(
+            tensor.images.row(1).to_vec()[..4],
+            [0., 60. / 255., 120. / 255., 180. / 255.]
+        );
+        assert_eq!(data.get(1).image.dim(), (1, 16));
+
+        let data = data.color(ColorMode::Rgb).normalize(false);
+        assert!(data.to_tensor().is_err());
+        let (images, labels) = data.to_array4().unwrap();
+        assert_eq!(images.dim(), (2, 3, 4, 4));
+        assert_eq!(labels.to_vec(), vec![0, 1]);
+        assert_eq!(
+            (
+                images[[0, 0, 1, 1]],
+                images[[0, 1, 1, 1]],
+                images[[0, 2, 1, 1]]
+            ),
+            (255., 0., 51.)
+        );
+    }
+}
@@ -5,15 +5,19 @@ extern crate ndarray;
 extern crate reqwest;
 extern crate tokio;
 
-use super::super::utils::fetch_client::{FConf, FetchClient, RemoteFile};
+use super::super::utils::fetch_client::{
+    block_on, ClientOptions, FConf, FetchClient, HashAlgorithm, RemoteFile,
+};
 use super::super::utils::natural_transform::to_io;
-use byteorder::{BigEndian, ReadBytesExt};
-use failure::Error;
-use libflate::gzip::Decoder;
-use ndarray::{stack, Array2, Axis};
+use super::idx;
+use super::idx_mmap::{self, LazyMnist};
+use super::normalize::{Normalization, Normalizer};
+use super::tensor_cache::{self, MappedTensor};
+use super::LoadOptions;
+use ndarray::{s, stack, Array1, Array2, ArrayD, ArrayView1, ArrayView2, ArrayView4, Axis};
 use std::fmt;
-use std::fs::File;
-use std::io::{self, BufReader, BufWriter, Cursor, Read};
+use std::io;
+use std::thread;
 use std::vec;
 
 #[repr(usize)]
@@ -31,6 +35,13 @@ pub struct DatasetKey {
     label: KeyFile,
 }
 
+impl DatasetKey {
+    /// `is_train` checks if the key specifies the training set
+    pub fn is_train(&self) -> bool {
+        matches!(self.img, KeyFile::TrainImg)
+    }
+}
+
 pub fn train_dataset() -> DatasetKey {
     DatasetKey {
         img: KeyFile::TrainImg,
@@ -45,54 +56,18 @@ pub fn test_dataset() -> DatasetKey {
     }
 }
 
-struct MnistData {
-    sizes: vec::Vec<i32>,
-    data: vec::Vec<u8>,
-}
-
-fn unarchive_gz(client: &FetchClient, fname: &str) -> Result<vec::Vec<u8>, Error> {
-    if client.dir_client.exists() && client.dir_client.file_exists(fname) {
-        let src_path = client.dir_client.file_path(fname);
-        let sf = BufReader::new(File::open(src_path).unwrap());
-        let mut buf = vec![];
-        {
-            let mut df = BufWriter::new(&mut buf);
-            let mut decoder = Decoder::new(sf).expect("failed to construct decoder");
-            io::copy(&mut decoder, &mut df).expect("failed to copy file");
-        }
-        Ok(buf)
+fn unarchive_mnist(client: &FetchClient, fname: &str) -> io::Result<ArrayD<u8>> {
+    // The label files have one dimension (number of items) and
+    // the image files have three dimensions (number of images, rows and columns).
+    // See also: `setup::idx`
+    let path = client.dir_client.file_path(fname);
+    // The archive may have been removed after `idx_mmap::decompress_once`.
+    let path = if path.exists() {
+        path
     } else {
-        Err(failure::format_err!("no such file or directory"))
-    }
-}
-
-fn unarchive_mnist(client: &FetchClient, fname: &str) -> io::Result<MnistData> {
-    let s = to_io(unarchive_gz(&client, fname), io::ErrorKind::NotFound)?;
-    let mut r = Cursor::new(&s);
-
-    // Read the magic number indicating the data type.
-    // If it is 2049, the data type is TRAINING SET LABEL FILE or TEST SET LABEL FILE.
-    // It it is 2051, the data type is TRAINING SET IMAGE FILE or TEST SET IMAGE FILE.
-    // See also: FILE FORMATS FOR THE MNIST DATABASE of http://yann.lecun.com/exdb/mnist/
-    let magic_number = r.read_i32::<BigEndian>()?;
-
-    let mut sizes: vec::Vec<i32> = vec::Vec::new();
-    let mut data: vec::Vec<u8> = vec::Vec::new();
-
-    match magic_number {
-        2049 => sizes.push(r.read_i32::<BigEndian>()?), // number of items
-        2051 => {
-            sizes.push(r.read_i32::<BigEndian>()?); // number of images
-            sizes.push(r.read_i32::<BigEndian>()?); // number of rows
-            sizes.push(r.read_i32::<BigEndian>()?); // number of columns
-        }
-        _ => return Err(io::Error::new(io::ErrorKind::Other, "unexpected value")),
-    }
-    r.read_to_end(&mut data)?;
-    Ok(MnistData {
-        sizes: sizes,
-        data: data,
-    })
+        idx_mmap::raw_path(&path)?
+    };
+    idx::read_file(path)?.into_u8()
 }
 
 #[derive(Debug, Clone)]
@@ -111,8 +86,56 @@ impl fmt::Display for MnistImage {
     }
 }
 
+const URL_BASE: &str = "http://yann.lecun.com/exdb/mnist/";
+pub(super) const MNIST_SAVE_DIR: &str = ".mnist";
+
+pub(super) static FILES: [RemoteFile; 4] = [
+    RemoteFile {
+        host_and_path: URL_BASE,
+        fname: "train-images-idx3-ubyte.gz",
+        digest: "440fcabf73cc546fa21475e81ea370265605f56be210a4024d2ca8f203523609",
+        algorithm: HashAlgorithm::Sha256,
+        query: "",
+        archive: None,
+    },
+    RemoteFile {
+        host_and_path: URL_BASE,
+        fname: "train-labels-idx1-ubyte.gz",
+        digest: "3552534a0a558bbed6aed32b30c495cca23d567ec52cac8be1a0730e8010255c",
+        algorithm: HashAlgorithm::Sha256,
+        query: "",
+        archive: None,
+    },
+    RemoteFile {
+        host_and_path: URL_BASE,
+        fname: "t10k-images-idx3-ubyte.gz",
+        digest: "8d422c7b0a1c1c79245a5bcf07fe86e33eeafee792b84584aec276f5a2dbc4e6",
+        algorithm: HashAlgorithm::Sha256,
+        query: "",
+        archive: None,
+    },
+    RemoteFile {
+        host_and_path: URL_BASE,
+        fname: "t10k-labels-idx1-ubyte.gz",
+        digest: "f7ae60f92e00ec6debd23a6088c31dbd2371eca3ffa0defaefb259924204aec6",
+        algorithm: HashAlgorithm::Sha256,
+        query: "",
+        archive: None,
+    },
+];
+
+/// `fetch_client` constructs the `FetchClient` of the MNIST files.
+///
+/// # Arguments
+///
+/// * `opts` - Settings of `FetchClient`.
+pub fn fetch_client(opts: &ClientOptions) -> io::Result<FetchClient<'static>> {
+    FetchClient::with_options(FConf::new(MNIST_SAVE_DIR, FILES.iter()), opts)
+}
+
 /// Loading MNIST Data (<http://yann.lecun.com/exdb/mnist/>).
-/// If the following files are not found in the .mnist directory of the execution path,
+/// If the following files are not found in the `mnist` directory of the cache root
+/// (See `utils::fetch_client::CacheRoot`),
 /// download them from the database and decode the data.
 ///
 /// * train-images-idx3-ubyte.gz:  training set images (9912422 bytes)
@@ -121,96 +144,357 @@ impl fmt::Display for MnistImage {
 /// * t10k-labels-idx1-ubyte.gz:   test set labels (4542 bytes)
 ///
 /// If they existed, read that file and decode the data.
+/// If `LoadOptions::decoded_cache` is enabled, the decoded data is cached next to the files
+/// (See `setup::tensor_cache`), so the next call loads it without decoding.
 ///
 /// # Arguments
 ///
 /// * `dataset_key` - `train_dataset()` or `test_dataset()`.
 /// * `normalize` - Flag that determines whether the image is normalized between 0.0 and 1.0.
 pub fn load_data(dataset_key: DatasetKey, normalize: bool) -> io::Result<vec::Vec<MnistImage>> {
-    const URL_BASE: &'static str = "http://yann.lecun.com/exdb/mnist/";
-    const MNIST_SAVE_DIR: &'static str = ".mnist";
-
-    static FILES: [RemoteFile; 4] = [
-        RemoteFile {
-            host_and_path: URL_BASE,
-            fname: "train-images-idx3-ubyte.gz",
-            sha256: "440fcabf73cc546fa21475e81ea370265605f56be210a4024d2ca8f203523609",
-            query: "",
-        },
-        RemoteFile {
-            host_and_path: URL_BASE,
-            fname: "train-labels-idx1-ubyte.gz",
-            sha256: "3552534a0a558bbed6aed32b30c495cca23d567ec52cac8be1a0730e8010255c",
-            query: "",
-        },
-        RemoteFile {
-            host_and_path: URL_BASE,
-            fname: "t10k-images-idx3-ubyte.gz",
-            sha256: "8d422c7b0a1c1c79245a5bcf07fe86e33eeafee792b84584aec276f5a2dbc4e6",
-            query: "",
-        },
-        RemoteFile {
-            host_and_path: URL_BASE,
-            fname: "t10k-labels-idx1-ubyte.gz",
-            sha256: "f7ae60f92e00ec6debd23a6088c31dbd2371eca3ffa0defaefb259924204aec6",
-            query: "",
-        },
-    ];
-
-    let mnist = FetchClient::new(FConf::new(MNIST_SAVE_DIR, FILES.iter()))?;
+    load_data_with(dataset_key, normalize, &LoadOptions::default())
+}
+
+/// `load_data_with` is the same as `load_data`
+/// except that the download and decoding are set up by `opts`
+/// (e.g. `ClientOptions::new().silent().into()` to report nothing).
+///
+/// # Arguments
+///
+/// * `dataset_key` - `train_dataset()` or `test_dataset()`.
+/// * `normalize` - Flag that determines whether the image is normalized between 0.0 and 1.0.
+/// * `opts` - Settings of `FetchClient` and the decoded cache.
+pub fn load_data_with(
+    dataset_key: DatasetKey,
+    normalize: bool,
+    opts: &LoadOptions,
+) -> io::Result<vec::Vec<MnistImage>> {
+    Ok(load_tensor_with(dataset_key, normalize, opts)?.to_images())
+}
+
+/// `load_tensor` is the same as `load_data`
+/// except that it returns the whole dataset as one contiguous `MnistTensor`
+/// instead of a vector of `MnistImage`.
+///
+/// # Arguments
+///
+/// * `dataset_key` - `train_dataset()` or `test_dataset()`.
+/// * `normalize` - Flag that determines whether the image is normalized between 0.0 and 1.0.
+pub fn load_tensor(dataset_key: DatasetKey, normalize: bool) -> io::Result<MnistTensor> {
+    load_tensor_with(dataset_key, normalize, &LoadOptions::default())
+}
+
+/// `load_tensor_with` is the same as `load_tensor`
+/// except that the download and decoding are set up by `opts`.
+///
+/// # Arguments
+///
+/// * `dataset_key` - `train_dataset()` or `test_dataset()`.
+/// * `normalize` - Flag that determines whether the image is normalized between 0.0 and 1.0.
+/// * `opts` - Settings of `FetchClient` and the decoded cache.
+pub fn load_tensor_with(
+    dataset_key: DatasetKey,
+    normalize: bool,
+    opts: &LoadOptions,
+) -> io::Result<MnistTensor> {
+    block_on(load_tensor_with_async(dataset_key, normalize, opts))
+}
+
+/// `load_data_async` is the same as `load_data`
+/// except that it runs on the runtime of the caller instead of creating its own,
+/// so it can be called from an async application.
+/// The decoding runs on the blocking threads of the runtime.
+///
+/// # Arguments
+///
+/// * `dataset_key` - `train_dataset()` or `test_dataset()`.
+/// * `normalize` - Flag that determines whether the image is normalized between 0.0 and 1.0.
+pub async fn load_data_async(
+    dataset_key: DatasetKey,
+    normalize: bool,
+) -> io::Result<vec::Vec<MnistImage>> {
+    load_data_with_async(dataset_key, normalize, &LoadOptions::default()).await
+}
+
+/// `load_data_with_async` is the same as `load_data_async`
+/// except that the download and decoding are set up by `opts`
+pub async fn load_data_with_async(
+    dataset_key: DatasetKey,
+    normalize: bool,
+    opts: &LoadOptions,
+) -> io::Result<vec::Vec<MnistImage>> {
+    Ok(load_tensor_with_async(dataset_key, normalize, opts)
+        .await?
+        .to_images())
+}
+
+/// `load_tensor_with_async` is the async variant of `load_tensor_with`
+pub async fn load_tensor_with_async(
+    dataset_key: DatasetKey,
+    normalize: bool,
+    opts: &LoadOptions,
+) -> io::Result<MnistTensor> {
+    let mnist = fetch_client(&opts.client)?;
+    mnist.get_async().await?;
+
+    let img_fname = FILES[dataset_key.img as usize].fname;
+    let label_fname = FILES[dataset_key.label as usize].fname;
+    let decoded_cache = opts.decoded_cache;
+    // Both the cache and the decoding read the disk, so they run on the blocking threads.
+    tokio::task::spawn_blocking(move || {
+        let build = || decode(mnist.clone(), img_fname, label_fname);
+        if !decoded_cache {
+            let (images, labels) = build()?;
+            return MnistTensor::from_idx(images, labels, normalize);
+        }
+        tensor_cache::load_or_build(&mnist, &[img_fname, label_fname], "", normalize, build)
+    })
+    .await?
+}
+
+/// `load_mapped` is the same as `load_data`
+/// except that the decoded data is cached next to the files (See `setup::tensor_cache`)
+/// and the images are read on demand from the memory-mapped cache.
+/// The cache is created regardless of `LoadOptions::decoded_cache`.
+///
+/// # Arguments
+///
+/// * `dataset_key` - `train_dataset()` or `test_dataset()`.
+/// * `normalize` - Flag that determines whether the image is normalized between 0.0 and 1.0.
+pub fn load_mapped(dataset_key: DatasetKey, normalize: bool) -> io::Result<MappedTensor> {
+    load_mapped_with(dataset_key, normalize, &ClientOptions::default())
+}
+
+/// `load_mapped_with` is the same as `load_mapped`
+/// except that the download and decoding are set up by `opts`.
+pub fn load_mapped_with(
+    dataset_key: DatasetKey,
+    normalize: bool,
+    opts: &ClientOptions,
+) -> io::Result<MappedTensor> {
+    let mnist = fetch_client(opts)?;
     mnist.get()?;
+    let img_fname = FILES[dataset_key.img as usize].fname;
+    let label_fname = FILES[dataset_key.label as usize].fname;
+    let mapped = tensor_cache::load_mapped_or_build(&mnist, &[img_fname, label_fname], "", || {
+        decode(mnist.clone(), img_fname, label_fname)
+    })?;
+    Ok(mapped.normalize(normalize))
+}
 
-    let task = |key_idx: KeyFile, mnistl: FetchClient| -> io::Result<MnistData> {
-        println!(
-            "start to decode {}...",
-            FILES[key_idx.clone() as usize].fname
-        );
-        let ret = unarchive_mnist(&mnistl, FILES[key_idx.clone() as usize].fname);
-        println!("Complete to decode {}", FILES[key_idx as usize].fname);
+/// `load_lazy` is the same as `load_data`
+/// except that the images are read on demand from the memory-mapped files.
+/// The files are decompressed to the disk at the first call,
+/// so the whole dataset is never loaded into memory.
+///
+/// # Arguments
+///
+/// * `dataset_key` - `train_dataset()` or `test_dataset()`.
+/// * `normalize` - Flag that determines whether the image is normalized between 0.0 and 1.0.
+pub fn load_lazy(dataset_key: DatasetKey, normalize: bool) -> io::Result<LazyMnist> {
+    load_lazy_with(dataset_key, normalize, &ClientOptions::default())
+}
+
+/// `load_lazy_with` is the same as `load_lazy`
+/// except that the download is set up by `opts`.
+pub fn load_lazy_with(
+    dataset_key: DatasetKey,
+    normalize: bool,
+    opts: &ClientOptions,
+) -> io::Result<LazyMnist> {
+    let mnist = fetch_client(opts)?;
+    mnist.get()?;
+    idx_mmap::open_lazy(
+        &mnist,
+        FILES[dataset_key.img as usize].fname,
+        FILES[dataset_key.label as usize].fname,
+        normalize,
+    )
+}
+
+/// `decode` decodes the image file and the label file saved by `client` in parallel.
+/// It blocks the current thread, so the async callers run it on the blocking threads.
+pub(super) fn decode(
+    client: FetchClient<'static>,
+    img_fname: &'static str,
+    label_fname: &'static str,
+) -> io::Result<(ArrayD<u8>, ArrayD<u8>)> {
+    let task = |fname: &str, client: &FetchClient| -> io::Result<ArrayD<u8>> {
+        client.progress().decode_start(fname);
+        let ret = unarchive_mnist(client, fname);
+        client.progress().decode_finish(fname);
         ret
     };
 
-    let mut rt = tokio::runtime::Runtime::new()?;
-    let res: io::Result<(io::Result<MnistData>, io::Result<MnistData>)> = rt.block_on(async move {
-        let label = dataset_key.label;
-        let img = dataset_key.img;
-        let mnistl = mnist.clone();
-        let res_label = tokio::spawn(async move { task(label, mnist) }).await?;
-        let res_data = tokio::spawn(async move { task(img, mnistl) }).await?;
-        Ok((res_label, res_data))
-    });
-    let (label, images) = res?;
-    let label_data = label?;
-    let images_data = images?;
-
-    let mut images: vec::Vec<Array2<f64>> = vec::Vec::new();
-    let image_shape = (images_data.sizes[1] * images_data.sizes[2]) as usize;
-    let compute_normalize: Box<dyn Fn(vec::Vec<u8>) -> vec::Vec<f64>> = if normalize {
-        Box::new(|img_data: vec::Vec<u8>| -> vec::Vec<f64> {
-            img_data.into_iter().map(|x| x as f64 / 255.).collect()
-        })
-    } else {
-        Box::new(|img_data: vec::Vec<u8>| -> vec::Vec<f64> {
-            img_data.into_iter().map(|x| x as f64).collect()
+    let clientl = client.clone();
+    let label = thread::spawn(move || task(label_fname, &clientl));
+    let images = task(img_fname, &client)?;
+    // A panic while decoding the labels is passed on to the caller as it is.
+    let label = label
+        .join()
+        .unwrap_or_else(|e| std::panic::resume_unwind(e))?;
+    Ok((images, label))
+}
+
+/// `load_normalized` loads the training and test splits normalized by `mode`.
+/// The statistics of the normalization are computed from the training split and
+/// applied to both splits. The returned `Normalizer` applies the same transformation
+/// to the images at inference time.
+///
+/// # Arguments
+///
+/// * `mode` - The mode of normalization
+pub fn load_normalized(mode: Normalization) -> io::Result<(MnistTensor, MnistTensor, Normalizer)> {
+    load_normalized_with(mode, &LoadOptions::default())
+}
+
+/// `load_normalized_with` is the same as `load_normalized`
+/// except that the download and decoding are set up by `opts`
+pub fn load_normalized_with(
+    mode: Normalization,
+    opts: &LoadOptions,
+) -> io::Result<(MnistTensor, MnistTensor, Normalizer)> {
+    let mut train = load_tensor_with(train_dataset(), false, opts)?;
+    let mut test = load_tensor_with(test_dataset(), false, opts)?;
+    let normalizer = Normalizer::fit(mode, &train.images)?;
+    train.normalize(&normalizer)?;
+    test.normalize(&normalizer)?;
+    Ok((train, test, normalizer))
+}
+
+/// `MnistTensor` holds the whole dataset in contiguous arrays.
+/// The batches are taken from it as views without copying the images.
+#[derive(Debug, Clone)]
+pub struct MnistTensor {
+    /// Images of shape (N, rows * columns)
+    pub images: Array2<f64>,
+    /// Labels of shape (N)
+    pub labels: Array1<u8>,
+    /// The number of rows of an image
+    pub rows: usize,
+    /// The number of columns of an image
+    pub cols: usize,
+}
+
+impl MnistTensor {
+    /// `from_idx` constructs `MnistTensor` from the decoded IDX files
+    ///
+    /// # Arguments
+    ///
+    /// * `images` - Images of shape (N, rows, columns)
+    /// * `labels` - Labels of shape (N)
+    /// * `normalize` - Flag that determines whether the image is normalized between 0.0 and 1.0.
+    pub fn from_idx(images: ArrayD<u8>, labels: ArrayD<u8>, normalize: bool) -> io::Result<Self> {
+        let (n, rows, cols) = match *images.shape() {
+            [n, rows, cols] => (n, rows, cols),
+            ref shape => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "the images must have 3 dimensions, but the shape is {:?}",
+                        shape
+                    ),
+                ))
+            }
+        };
+        if labels.shape() != [n] {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "the number of labels {:?} does not match the number of images ({})",
+                    labels.shape(),
+                    n
+                ),
+            ));
+        }
+        let scale = if normalize { 255. } else { 1. };
+        let images = to_io(
+            images.into_shape((n, rows * cols)),
+            io::ErrorKind::InvalidData,
+        )?;
+        let labels = to_io(labels.into_shape(n), io::ErrorKind::InvalidData)?;
+        Ok(Self {
+            images: images.mapv(|x| f64::from(x) / scale),
+            labels,
+            rows,
+            cols,
         })
-    };
+    }
 
-    let shape = (1, image_shape);
-    for i in 0..images_data.sizes[0] as usize {
-        let start = i * image_shape;
-        let end = start + image_shape;
-        let image_data = images_data.data[start..end].to_vec(); // 0~784, 784~(784 * 2), (784 * 2)~(784 * 3) ...
-        images.push(Array2::from_shape_vec(shape, compute_normalize(image_data)).unwrap());
+    /// `normalize` normalizes the raw images by `normalizer`
+    pub fn normalize(&mut self, normalizer: &Normalizer) -> io::Result<()> {
+        normalizer.apply(&mut self.images)
     }
 
-    let mut ret: Vec<MnistImage> = vec::Vec::new();
-    for (img, lbl) in images.into_iter().zip(label_data.data.into_iter()) {
-        ret.push(MnistImage {
-            image: img,
-            label: lbl,
-        })
+    /// `len` returns the number of images
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// `is_empty` checks if there are no images
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// `images_4d` returns the view of the images of shape (N, 1, rows, columns)
+    pub fn images_4d(&self) -> ArrayView4<'_, f64> {
+        self.images
+            .view()
+            .into_shape((self.len(), 1, self.rows, self.cols))
+            .unwrap()
+    }
+
+    /// `batch` returns the view of `bsize` images from the `i`th one.
+    /// The batch is truncated at the end of the dataset.
+    pub fn batch(&self, i: usize, bsize: usize) -> BatchView<'_> {
+        let start = i.min(self.len());
+        let end = i.saturating_add(bsize).min(self.len());
+        BatchView {
+            images: self.images.slice(s![start..end, ..]),
+            labels: self.labels.slice(s![start..end]),
+        }
+    }
+
+    /// `batches` iterates over the views of the batches in order.
+    /// The last batch is smaller than `bsize` if the size of the dataset is not divisible by it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bsize` is zero.
+    pub fn batches(&self, bsize: usize) -> impl Iterator<Item = BatchView<'_>> {
+        assert!(bsize != 0, "The batch size must be non-zero");
+        (0..self.len())
+            .step_by(bsize)
+            .map(move |i| self.batch(i, bsize))
+    }
+
+    /// `to_images` copies each image to `MnistImage` of shape (1, rows * columns)
+    pub fn to_images(&self) -> vec::Vec<MnistImage> {
+        self.images
+            .outer_iter()
+            .zip(self.labels.iter())
+            .map(|(image, &label)| MnistImage {
+                image: image.insert_axis(Axis(0)).to_owned(),
+                label,
+            })
+            .collect()
+    }
+}
+
+/// `BatchView` is a batch that borrows the images and labels of `MnistTensor`
+#[derive(Debug, Clone)]
+pub struct BatchView<'a> {
+    /// Images of shape (batch size, rows * columns)
+    pub images: ArrayView2<'a, f64>,
+    /// Labels of shape (batch size)
+    pub labels: ArrayView1<'a, u8>,
+}
+
+impl<'a> BatchView<'a> {
+    /// `batch_size` returns the number of images in the batch
+    pub fn batch_size(&self) -> usize {
+        self.labels.len()
     }
-    Ok(ret)
 }
 
 #[derive(Debug)]
@@ -236,6 +520,8 @@ impl Batched {
 ///
 /// * `mnist_images` - mnist images
 /// * `bsize` - Specify the batch size. Batch size must be non-zero and divisible by data size (10000)
+///
+/// See also `data_loader::DataLoader`, which shuffles the data and allows the remainder.
 pub fn batched(mnist_images: vec::Vec<MnistImage>, bsize: usize) -> io::Result<vec::Vec<Batched>> {
     if bsize == 0 {
         return Err(io::Error::new(
@@ -272,4 +558,74 @@ pub fn batched(mnist_images: vec::Vec<MnistImage>, bsize: usize) -> io::Result<v
         i += bsize;
     }
     Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::utils::fetch_client::CacheRoot;
+    use super::*;
+    use ndarray::IxDyn;
+
+    #[test]
+    fn test_tensor_batches() {
+        let images = ArrayD::from_shape_vec(IxDyn(&[5, 2, 2]), (0..20).collect()).unwrap();
+        let labels = ArrayD::from_shape_vec(IxDyn(&[5]), vec![0, 1, 2, 3, 4]).unwrap();
+        let tensor = MnistTensor::from_idx(images, labels, false).unwrap();
+        assert_eq!(tensor.images.dim(), (5, 4));
+        assert_eq!(tensor.images_4d().dim(), (5, 1, 2, 2));
+        assert_eq!(tensor.images_4d()[[1, 0, 1, 0]], 6.);
+
+        let batches = tensor.batches(2).collect::<Vec<_>>();
+        assert_eq!(
+            batches
+                .iter()
+                .map(BatchView::batch_size)
+                .collect::<Vec<_>>(),
+            vec![2, 2, 1]
+        );
+        assert_eq!(batches[2].labels.to_vec(), vec![4]);
+        assert_eq!(
+            batches[1].images.row(0).as_ptr(),
+            tensor.images.row(2).as_ptr()
+        );
+
+        let images = tensor.to_images();
+        assert_eq!(images[3].image.dim(), (1, 4));
+        assert_eq!(images[3].label, 3);
+    }
+
+    #[test]
+    fn test_tensor_rejects_mismatched_labels() {
+        let images = ArrayD::<u8>::zeros(IxDyn(&[3, 2, 2]));
+        let labels = ArrayD::<u8>::zeros(IxDyn(&[2]));
+        assert!(MnistTensor::from_idx(images, labels, true).is_err());
+    }
+
+    #[test]
+    fn test_load_tensor_async_inside_runtime() {
+        let root = tempfile::tempdir().unwrap();
+        let opts = ClientOptions::new()
+            .silent()
+            .cache_root(CacheRoot::Dir(root.path().to_path_buf()));
+        // The saved files are used without download.
+        let dir = fetch_client(&opts).unwrap().dir_client.path().to_path_buf();
+        std::fs::create_dir_all(&dir).unwrap();
+        let images = ArrayD::from_shape_fn(IxDyn(&[3, 2, 2]), |i| (i[0] * 4) as u8);
+        let labels = ArrayD::from_shape_vec(IxDyn(&[3]), vec![7, 8, 9]).unwrap();
+        for (i, file) in FILES.iter().enumerate() {
+            let data = if i % 2 == 0 { &images } else { &labels };
+            idx::write_file(dir.join(file.fname), &idx::IdxData::UByte(data.clone())).unwrap();
+        }
+
+        let load_opts = LoadOptions::from(opts);
+        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        let tensor = rt
+            .block_on(async { load_tensor_with_async(test_dataset(), false, &load_opts).await })
+            .unwrap();
+        assert_eq!(tensor.labels.to_vec(), vec![7, 8, 9]);
+        assert_eq!(tensor.images.row(2).to_vec(), vec![8.; 4]);
+
+        let data = load_data_with(test_dataset(), false, &load_opts).unwrap();
+        assert_eq!(data[1].image, tensor.images.slice(s![1..2, ..]));
+    }
 }
\ No newline at end of file
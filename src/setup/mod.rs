@@ -1,3 +1,95 @@
 
+pub mod augment;
+pub mod cifar;
+pub mod data_loader;
 pub mod dlfs;
-pub mod mnist;
\ No newline at end of file
+pub mod export;
+pub mod idx;
+pub mod idx_dataset;
+pub mod idx_mmap;
+pub mod image_folder;
+pub mod mnist;
+pub mod normalize;
+pub mod prefetch;
+pub mod random;
+pub mod split;
+pub mod stats;
+pub mod tensor_cache;
+pub mod toy;
+
+use super::utils::fetch_client::{ClientOptions, FetchClient};
+use std::io;
+
+/// The names of the datasets that `fetch_client` can construct.
+/// `fetch_client` also accepts the names of the descriptors in `idx_dataset` (e.g. `emnist-letters`),
+/// but all the splits of EMNIST share the same files as `emnist`.
+pub const DATASET_NAMES: &[&str] = &[
+    "mnist",
+    "fashion-mnist",
+    "kmnist",
+    "emnist",
+    "cifar-10",
+    "cifar-100",
+    "chap3",
+];
+
+/// Settings of the loaders which decode the datasets (e.g. `mnist::load_data_with`)
+#[derive(Clone, Default)]
+pub struct LoadOptions {
+    /// Settings of `FetchClient` used to get the files of the dataset
+    pub client: ClientOptions,
+    /// Whether the decoded datasets are cached next to the files (see `tensor_cache`).
+    /// It is disabled by default since the cache takes as much disk space as the raw images.
+    pub decoded_cache: bool,
+}
+
+impl LoadOptions {
+    /// `LoadOptions` constructor. The files are got by `ClientOptions::default()`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `client` sets the settings of `FetchClient`
+    pub fn client(mut self, client: ClientOptions) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// `decoded_cache` sets whether the decoded datasets are cached next to the files
+    pub fn decoded_cache(mut self, enabled: bool) -> Self {
+        self.decoded_cache = enabled;
+        self
+    }
+}
+
+impl From<ClientOptions> for LoadOptions {
+    fn from(client: ClientOptions) -> Self {
+        Self::new().client(client)
+    }
+}
+
+/// `fetch_client` constructs the `FetchClient` of the dataset named `name`.
+///
+/// # Arguments
+///
+/// * `name` - One of `DATASET_NAMES` or the name of `idx_dataset::IdxDataset`.
+/// * `opts` - Settings of `FetchClient`.
+pub fn fetch_client(name: &str, opts: &ClientOptions) -> io::Result<FetchClient<'static>> {
+    match name {
+        "mnist" => mnist::fetch_client(opts),
+        "cifar-10" => cifar::cifar10_fetch_client(opts),
+        "cifar-100" => cifar::cifar100_fetch_client(opts),
+        "chap3" => dlfs::chap3::fetch_client(opts),
+        _ => match idx_dataset::by_name(name) {
+            Some(dataset) => dataset.fetch_client(opts),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "unknown dataset: {} (available: {})",
+                    name,
+                    DATASET_NAMES.join(", ")
+                ),
+            )),
+        },
+    }
+}
\ No newline at end of file
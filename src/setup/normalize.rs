@@ -0,0 +1,160 @@
+use ndarray::{Array1, Array2, Axis};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::vec;
+
+/// The maximum value of a pixel
+const MAX_PIXEL: f64 = 255.;
+
+/// `Normalization` decides how the raw pixel values between 0 and 255 are normalized
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Normalization {
+    /// The raw values between 0 and 255
+    Raw,
+    /// Scale to [0, 1]. This is the same as `normalize = true` of `mnist::load_data`.
+    UnitRange,
+    /// Scale to [-1, 1]
+    SymmetricRange,
+    /// Subtract the mean and divide by the standard deviation of each pixel
+    /// computed from the training split
+    PixelZScore,
+    /// Subtract the mean and divide by the standard deviation of all the pixels
+    /// computed from the training split
+    GlobalZScore,
+    /// 1 if the value scaled to [0, 1] is greater than the threshold, otherwise 0
+    Binarize(f64),
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Normalization::Raw
+    }
+}
+
+/// `Normalizer` is the normalization fitted to the training split.
+/// It keeps the mean and standard deviation, so the same transformation can be applied
+/// to the test split or to the images at inference time.
+/// It can be saved with serde.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Normalizer {
+    /// The mode of normalization
+    pub mode: Normalization,
+    /// The mean of each pixel, or the single mean shared by all the pixels
+    pub mean: vec::Vec<f64>,
+    /// The standard deviation of each pixel, or the single one shared by all the pixels
+    pub std: vec::Vec<f64>,
+}
+
+impl Normalizer {
+    /// `fit` computes the statistics of `images` required by `mode`.
+    /// The statistics of the modes other than z-score are constants
+    /// (e.g. mean 0 and std 255 for `UnitRange`), so `images` is not used for them.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - The mode of normalization
+    /// * `images` - The raw images of the training split of shape (N, pixels)
+    pub fn fit(mode: Normalization, images: &Array2<f64>) -> io::Result<Self> {
+        let (mean, std) = match mode {
+            Normalization::Raw | Normalization::Binarize(_) => (vec![0.], vec![1.]),
+            Normalization::UnitRange => (vec![0.], vec![MAX_PIXEL]),
+            Normalization::SymmetricRange => (vec![MAX_PIXEL / 2.], vec![MAX_PIXEL / 2.]),
+            Normalization::PixelZScore => {
+                let mean = images.mean_axis(Axis(0)).ok_or_else(empty_error)?;
+                let std = images.std_axis(Axis(0), 0.);
+                (mean.to_vec(), std.iter().map(|&s| nonzero(s)).collect())
+            }
+            Normalization::GlobalZScore => {
+                let mean = images.mean().ok_or_else(empty_error)?;
+                let std = images.mapv(|x| (x - mean).powi(2)).mean().unwrap().sqrt();
+                (vec![mean], vec![nonzero(std)])
+            }
+        };
+        Ok(Self { mode, mean, std })
+    }
+
+    /// `apply` normalizes the raw `images` of shape (N, pixels) in place
+    pub fn apply(&self, images: &mut Array2<f64>) -> io::Result<()> {
+        if let Normalization::Binarize(threshold) = self.mode {
+            images.mapv_inplace(|x| if x / MAX_PIXEL > threshold { 1. } else { 0. });
+            return Ok(());
+        }
+        if self.mean.len() == 1 {
+            let (mean, std) = (self.mean[0], self.std[0]);
+            images.mapv_inplace(|x| (x - mean) / std);
+            return Ok(());
+        }
+        if images.ncols() != self.mean.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "the number of pixels ({}) does not match the statistics ({})",
+                    images.ncols(),
+                    self.mean.len()
+                ),
+            ));
+        }
+        let mean = Array1::from(self.mean.clone());
+        let std = Array1::from(self.std.clone());
+        for mut row in images.outer_iter_mut() {
+            row -= &mean;
+            row /= &std;
+        }
+        Ok(())
+    }
+}
+
+// The pixels which never change (e.g. the corners of MNIST) are left as they are centered.
+fn nonzero(std: f64) -> f64 {
+    if std > 0. {
+        std
+    } else {
+        1.
+    }
+}
+
+fn empty_error() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "the statistics cannot be computed from no images",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::arr2;
+
+    #[test]
+    fn test_normalizations() {
+        let train = arr2(&[[0., 255., 10.], [255., 255., 30.]]);
+        let apply = |mode| {
+            let normalizer = Normalizer::fit(mode, &train).unwrap();
+            let mut images = train.clone();
+            normalizer.apply(&mut images).unwrap();
+            (normalizer, images)
+        };
+
+        assert_eq!(apply(Normalization::UnitRange).1[[1, 0]], 1.);
+        assert_eq!(apply(Normalization::SymmetricRange).1[[0, 0]], -1.);
+        assert_eq!(
+            apply(Normalization::Binarize(0.1)).1,
+            arr2(&[[0., 1., 0.], [1., 1., 1.]])
+        );
+
+        let (normalizer, images) = apply(Normalization::PixelZScore);
+        assert_eq!(normalizer.mean, vec![127.5, 255., 20.]);
+        assert_eq!(normalizer.std, vec![127.5, 1., 10.]);
+        assert_eq!(images, arr2(&[[-1., 0., -1.], [1., 0., 1.]]));
+
+        let (normalizer, images) = apply(Normalization::GlobalZScore);
+        assert_eq!(normalizer.mean.len(), 1);
+        assert!(images.mean().unwrap().abs() < 1e-12);
+        assert!((images.mapv(|x| x * x).mean().unwrap() - 1.).abs() < 1e-12);
+
+        let mut wrong = Array2::zeros((1, 2));
+        let normalizer = Normalizer::fit(Normalization::PixelZScore, &train).unwrap();
+        assert!(normalizer.apply(&mut wrong).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use super::data_loader::{Collate, DataLoader, Dataset};
+use std::io;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// `PrefetchLoader` assembles the batches of `DataLoader` on worker threads
+/// while the caller consumes the previous ones.
+/// The batches are yielded in the same order as `DataLoader::iter`,
+/// so the order is deterministic for the same seed and epoch regardless of the number of workers.
+///
+/// # Examples
+///
+/// ```
+/// use deep_learning_playground::setup::data_loader::DataLoader;
+/// use deep_learning_playground::setup::mnist::MnistImage;
+/// use deep_learning_playground::setup::prefetch::PrefetchLoader;
+/// use ndarray::Array2;
+///
+/// let data = (0..10)
+///     .map(|i| MnistImage { image: Array2::zeros((1, 4)), label: i })
+///     .collect::<Vec<_>>();
+/// let loader = DataLoader::new(data, 3).unwrap().shuffle(true).seed(1);
+/// let expected = loader.iter(0).unwrap().map(|b| b.labels).collect::<Vec<_>>();
+///
+/// let prefetch = PrefetchLoader::new(loader, 2, 4).unwrap();
+/// let labels = prefetch.iter(0).unwrap().map(|b| b.labels).collect::<Vec<_>>();
+/// assert_eq!(labels, expected);
+/// ```
+pub struct PrefetchLoader<D> {
+    loader: Arc<DataLoader<D>>,
+    workers: usize,
+    depth: usize,
+}
+
+impl<D> PrefetchLoader<D>
+where
+    D: Dataset + Send + Sync + 'static,
+    D::Item: Collate,
+    <D::Item as Collate>::Batch: Send + 'static,
+{
+    /// `PrefetchLoader` constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `loader` - The loader which decides the batches
+    /// * `workers` - The number of worker threads. It must be non-zero.
+    /// * `depth` - The number of batches each worker prepares ahead. It must be non-zero.
+    pub fn new(loader: DataLoader<D>, workers: usize, depth: usize) -> io::Result<Self> {
+        if workers == 0 || depth == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The number of workers and the depth of the queue must be non-zero",
+            ));
+        }
+        Ok(Self {
+            loader: Arc::new(loader),
+            workers,
+            depth,
+        })
+    }
+
+    /// `loader` returns the underlying `DataLoader`
+    pub fn loader(&self) -> &DataLoader<D> {
+        &self.loader
+    }
+
+    /// `iter` starts the workers and iterates over the batches of `epoch`.
+    /// The workers stop when the iterator is dropped.
+    pub fn iter(&self, epoch: usize) -> io::Result<PrefetchIter<<D::Item as Collate>::Batch>> {
+        let batches = self.loader.batch_indices(epoch)?;
+        let len = batches.len();
+        let workers = self.workers.min(len.max(1));
+        let mut receivers = Vec::with_capacity(workers);
+        let mut handles = Vec::with_capacity(workers);
+        for w in 0..workers {
+            // The `w`th worker prepares the batches w, w + workers, w + 2 * workers, ...
+            // and the iterator receives them from the workers in turn.
+            let assigned = batches
+                .iter()
+                .skip(w)
+                .step_by(workers)
+                .cloned()
+                .collect::<Vec<_>>();
+            let (tx, rx) = mpsc::sync_channel(self.depth);
+            let loader = Arc::clone(&self.loader);
+            handles.push(thread::spawn(move || {
+                let dataset = loader.dataset();
+                for indices in assigned {
+                    let batch = D::Item::collate(
+                        indices
+                            .into_iter()
+                            .map(|i| dataset.get_at(i, epoch))
+                            .collect(),
+                    );
+                    if tx.send(batch).is_err() {
+                        break;
+                    }
+                }
+            }));
+            receivers.push(rx);
+        }
+        Ok(PrefetchIter {
+            receivers,
+            handles,
+            next: 0,
+            len,
+        })
+    }
+}
+
+/// The iterator over the batches prepared by the workers of `PrefetchLoader`
+pub struct PrefetchIter<B> {
+    receivers: Vec<Receiver<B>>,
+    handles: Vec<JoinHandle<()>>,
+    next: usize,
+    len: usize,
+}
+
+impl<B> Iterator for PrefetchIter<B> {
+    type Item = B;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.len {
+            return None;
+        }
+        let rx = &self.receivers[self.next % self.receivers.len()];
+        self.next += 1;
+        match rx.recv() {
+            Ok(batch) => Some(batch),
+            Err(_) => panic!("a worker of PrefetchLoader has panicked"),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let rest = self.len - self.next;
+        (rest, Some(rest))
+    }
+}
+
+impl<B> ExactSizeIterator for PrefetchIter<B> {}
+
+impl<B> Drop for PrefetchIter<B> {
+    fn drop(&mut self) {
+        // Dropping the receivers makes the blocked workers fail to send and stop.
+        self.receivers.clear();
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mnist::MnistImage;
+    use super::*;
+    use ndarray::Array2;
+
+    fn dataset(n: usize) -> Vec<MnistImage> {
+        (0..n)
+            .map(|i| MnistImage {
+                image: Array2::from_elem((1, 2), i as f64),
+                label: (i % 256) as u8,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_same_order_as_data_loader() {
+        let loader = DataLoader::new(dataset(103), 8)
+            .unwrap()
+            .shuffle(true)
+            .seed(3);
+        let expected = loader
+            .iter(5)
+            .unwrap()
+            .map(|b| b.images)
+            .collect::<Vec<_>>();
+        for &workers in [1, 3, 16].iter() {
+            let prefetch = PrefetchLoader::new(loader.clone(), workers, 2).unwrap();
+            let it = prefetch.iter(5).unwrap();
+            assert_eq!(it.len(), 13);
+            assert_eq!(it.map(|b| b.images).collect::<Vec<_>>(), expected);
+        }
+    }
+
+    #[test]
+    fn test_stop_early() {
+        let loader = DataLoader::new(dataset(1000), 1).unwrap();
+        let prefetch = PrefetchLoader::new(loader, 4, 1).unwrap();
+        let first = prefetch
+            .iter(0)
+            .unwrap()
+            .take(3)
+            .map(|b| b.labels)
+            .collect::<Vec<_>>();
+        assert_eq!(first, vec![vec![0], vec![1], vec![2]]);
+        assert!(PrefetchLoader::new(DataLoader::new(dataset(1), 1).unwrap(), 0, 1).is_err());
+    }
+}
@@ -0,0 +1,32 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::f64::consts::PI;
+
+/// `standard_normal` draws a sample of the standard normal distribution
+/// by the Box-Muller transform.
+/// It is shared by the augmentations and the synthetic datasets,
+/// which are reproducible by seeding `rng`.
+pub fn standard_normal(rng: &mut StdRng) -> f64 {
+    let u1: f64 = 1. - rng.gen::<f64>();
+    let u2: f64 = rng.gen();
+    (-2. * u1.ln()).sqrt() * (2. * PI * u2).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_standard_normal() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let samples = (0..10000)
+            .map(|_| standard_normal(&mut rng))
+            .collect::<Vec<_>>();
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        let var = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / samples.len() as f64;
+        assert!(mean.abs() < 0.05, "{}", mean);
+        assert!((var - 1.).abs() < 0.05, "{}", var);
+        assert!(samples.iter().all(|x| x.is_finite()));
+    }
+}
@@ -0,0 +1,225 @@
+use super::data_loader::Dataset;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::BTreeMap;
+use std::io;
+use std::vec;
+
+/// `Subset` is the view of the samples of `dataset` at `indices`
+#[derive(Debug, Clone)]
+pub struct Subset<D> {
+    /// The original dataset
+    pub dataset: D,
+    /// The indices of the samples in the original dataset
+    pub indices: vec::Vec<usize>,
+}
+
+impl<D: Dataset> Subset<D> {
+    /// `Subset` constructor
+    pub fn new(dataset: D, indices: vec::Vec<usize>) -> Self {
+        Self { dataset, indices }
+    }
+}
+
+impl<D: Dataset> Dataset for Subset<D> {
+    type Item = D::Item;
+
+    fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    fn get(&self, index: usize) -> Self::Item {
+        self.dataset.get(self.indices[index])
+    }
+
+    fn label(&self, index: usize) -> Option<usize> {
+        self.dataset.label(self.indices[index])
+    }
+
+    fn get_at(&self, index: usize, epoch: usize) -> Self::Item {
+        self.dataset.get_at(self.indices[index], epoch)
+    }
+}
+
+// The indices grouped by class, each of which is shuffled.
+// All samples belong to one group if the dataset is not labeled.
+fn shuffled_groups<D: Dataset>(dataset: &D, rng: &mut StdRng) -> vec::Vec<vec::Vec<usize>> {
+    let mut groups: BTreeMap<Option<usize>, vec::Vec<usize>> = BTreeMap::new();
+    let labeled = (0..dataset.len()).all(|i| dataset.label(i).is_some());
+    for i in 0..dataset.len() {
+        let key = if labeled { dataset.label(i) } else { None };
+        groups.entry(key).or_default().push(i);
+    }
+    groups
+        .into_values()
+        .map(|mut group| {
+            group.shuffle(rng);
+            group
+        })
+        .collect()
+}
+
+/// `train_val_split` splits the indices of `dataset` into the training and validation sets.
+/// If the dataset is labeled (see `Dataset::label`), the split is stratified,
+/// i.e. each class is split in the same ratio.
+/// It returns the sorted indices of the training set and the validation set.
+///
+/// # Arguments
+///
+/// * `dataset` - Dataset to be split (e.g. the training split of MNIST)
+/// * `val_ratio` - The ratio of the validation set between 0.0 and 1.0
+/// * `seed` - The seed of the random number generator
+pub fn train_val_split<D: Dataset>(
+    dataset: &D,
+    val_ratio: f64,
+    seed: u64,
+) -> io::Result<(vec::Vec<usize>, vec::Vec<usize>)> {
+    if !(0.0..=1.0).contains(&val_ratio) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("The ratio must be between 0 and 1, but {}", val_ratio),
+        ));
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut train = Vec::new();
+    let mut val = Vec::new();
+    for group in shuffled_groups(dataset, &mut rng) {
+        let n_val = (group.len() as f64 * val_ratio).round() as usize;
+        val.extend_from_slice(&group[..n_val]);
+        train.extend_from_slice(&group[n_val..]);
+    }
+    train.sort_unstable();
+    val.sort_unstable();
+    Ok((train, val))
+}
+
+/// `KFold` holds the folds of k-fold cross-validation
+#[derive(Debug, Clone)]
+pub struct KFold {
+    folds: vec::Vec<vec::Vec<usize>>,
+}
+
+impl KFold {
+    /// `new` splits the indices of `dataset` into `k` folds.
+    /// If the dataset is labeled (see `Dataset::label`), the folds are stratified.
+    ///
+    /// # Arguments
+    ///
+    /// * `dataset` - Dataset to be split
+    /// * `k` - The number of folds, which must be between 2 and the size of the dataset
+    /// * `seed` - The seed of the random number generator
+    pub fn new<D: Dataset>(dataset: &D, k: usize, seed: u64) -> io::Result<Self> {
+        if k < 2 || k > dataset.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "The number of folds ({}) must be between 2 and the size of the dataset ({})",
+                    k,
+                    dataset.len()
+                ),
+            ));
+        }
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut folds = vec![Vec::new(); k];
+        // Deal the samples of each class to the folds in turn,
+        // continuing from the fold where the previous class ended so that the sizes stay even.
+        let mut next = 0;
+        for group in shuffled_groups(dataset, &mut rng) {
+            for i in group {
+                folds[next].push(i);
+                next = (next + 1) % k;
+            }
+        }
+        for fold in folds.iter_mut() {
+            fold.sort_unstable();
+        }
+        Ok(Self { folds })
+    }
+
+    /// `len` returns the number of folds
+    pub fn len(&self) -> usize {
+        self.folds.len()
+    }
+
+    /// `is_empty` checks if there are no folds
+    pub fn is_empty(&self) -> bool {
+        self.folds.is_empty()
+    }
+
+    /// `fold` returns the sorted indices of the training set and the validation set
+    /// whose validation set is the `i`th fold, or `None` if `i` is not less than the number of folds
+    pub fn fold(&self, i: usize) -> Option<(vec::Vec<usize>, vec::Vec<usize>)> {
+        let val = self.folds.get(i)?.clone();
+        let mut train = self
+            .folds
+            .iter()
+            .enumerate()
+            .filter(|&(j, _)| j != i)
+            .flat_map(|(_, fold)| fold.iter().cloned())
+            .collect::<Vec<_>>();
+        train.sort_unstable();
+        Some((train, val))
+    }
+
+    /// `iter` iterates over the pairs of the training set and the validation set of each fold
+    pub fn iter(&self) -> impl Iterator<Item = (vec::Vec<usize>, vec::Vec<usize>)> + '_ {
+        (0..self.len()).filter_map(move |i| self.fold(i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mnist::MnistImage;
+    use super::*;
+    use ndarray::Array2;
+
+    fn dataset(labels: &[u8]) -> Vec<MnistImage> {
+        labels
+            .iter()
+            .map(|&label| MnistImage {
+                image: Array2::zeros((1, 1)),
+                label,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_stratified_split() {
+        let labels = (0..100)
+            .map(|i| if i < 80 { 0 } else { 1 })
+            .collect::<Vec<_>>();
+        let data = dataset(&labels);
+        let (train, val) = train_val_split(&data, 0.25, 1).unwrap();
+        assert_eq!((train.len(), val.len()), (75, 25));
+        assert_eq!(val.iter().filter(|&&i| labels[i] == 1).count(), 5);
+        assert_eq!(train_val_split(&data, 0.25, 1).unwrap().1, val);
+        assert_ne!(train_val_split(&data, 0.25, 2).unwrap().1, val);
+
+        let subset = Subset::new(&data, val);
+        assert_eq!(subset.len(), 25);
+        assert!(train_val_split(&data, 1.5, 1).is_err());
+    }
+
+    #[test]
+    fn test_k_fold() {
+        let labels = (0..30).map(|i| (i % 3) as u8).collect::<Vec<_>>();
+        let data = dataset(&labels);
+        let kfold = KFold::new(&data, 5, 0).unwrap();
+        let mut seen = Vec::new();
+        for (train, val) in kfold.iter() {
+            assert_eq!((train.len(), val.len()), (24, 6));
+            // Each class appears twice in every fold.
+            for c in 0..3 {
+                assert_eq!(val.iter().filter(|&&i| labels[i] == c).count(), 2);
+            }
+            assert!(val.iter().all(|i| !train.contains(i)));
+            seen.extend(val);
+        }
+        seen.sort_unstable();
+        assert_eq!(seen, (0..30).collect::<Vec<_>>());
+        assert!(kfold.fold(4).is_some());
+        assert!(kfold.fold(5).is_none());
+        assert!(KFold::new(&data, 1, 0).is_err());
+    }
+}
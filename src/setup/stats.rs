@@ -0,0 +1,353 @@
+use super::super::utils::natural_transform::to_io;
+use super::data_loader::Dataset;
+use super::mnist::MnistImage;
+use image::{ImageBuffer, Luma};
+use ndarray::{Array1, Axis};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::vec;
+
+/// `DatasetStats` is the summary of an image classification dataset
+/// used to sanity-check it before training
+#[derive(Debug, Clone)]
+pub struct DatasetStats {
+    /// The number of images
+    pub len: usize,
+    /// The number of rows of an image
+    pub rows: usize,
+    /// The number of columns of an image
+    pub cols: usize,
+    /// The number of images of each class
+    pub class_counts: BTreeMap<u8, usize>,
+    /// The mean of each pixel of shape (rows * columns)
+    pub mean: Array1<f64>,
+    /// The standard deviation of each pixel of shape (rows * columns)
+    pub std: Array1<f64>,
+    /// The mean of all the pixels
+    pub global_mean: f64,
+    /// The standard deviation of all the pixels
+    pub global_std: f64,
+    /// The minimum and maximum of all the pixels
+    pub range: (f64, f64),
+    /// The range evenly divided by the bins of `histogram`
+    pub histogram_range: (f64, f64),
+    /// The number of pixels in each bin.
+    /// The pixels out of `histogram_range` are counted in the first or the last bin.
+    pub histogram: vec::Vec<usize>,
+    /// The mean image of each class of shape (rows * columns)
+    pub class_means: BTreeMap<u8, Array1<f64>>,
+}
+
+impl DatasetStats {
+    /// `compute` computes the statistics of `dataset`.
+    /// It reads the images twice: once to find the range of the histogram,
+    /// and once to compute the statistics (See `compute_with_range`).
+    ///
+    /// # Arguments
+    ///
+    /// * `dataset` - Dataset whose images have `rows * cols` pixels (e.g. `MnistTensor`)
+    /// * `rows` - The number of rows of an image
+    /// * `cols` - The number of columns of an image
+    /// * `bins` - The number of bins of the histogram, which must be non-zero
+    pub fn compute<D>(dataset: &D, rows: usize, cols: usize, bins: usize) -> io::Result<Self>
+    where
+        D: Dataset<Item = MnistImage>,
+    {
+        let mut range = (f64::INFINITY, f64::NEG_INFINITY);
+        for i in 0..dataset.len() {
+            for &x in dataset.get(i).image.iter() {
+                range = (range.0.min(x), range.1.max(x));
+            }
+        }
+        if range.0 > range.1 {
+            // No pixels. `compute_with_range` reports the error.
+            range = (0., 0.);
+        }
+        Self::compute_with_range(dataset, rows, cols, bins, range)
+    }
+
+    /// `compute_with_range` is the same as `compute`
+    /// except that the histogram divides the known `range` (e.g. (0, 255) for the raw images),
+    /// so it reads each image only once.
+    ///
+    /// # Arguments
+    ///
+    /// * `dataset` - Dataset whose images have `rows * cols` pixels (e.g. `MnistTensor`)
+    /// * `rows` - The number of rows of an image
+    /// * `cols` - The number of columns of an image
+    /// * `bins` - The number of bins of the histogram, which must be non-zero
+    /// * `range` - The minimum and maximum of the histogram
+    pub fn compute_with_range<D>(
+        dataset: &D,
+        rows: usize,
+        cols: usize,
+        bins: usize,
+        range: (f64, f64),
+    ) -> io::Result<Self>
+    where
+        D: Dataset<Item = MnistImage>,
+    {
+        if !(range.0.is_finite() && range.1.is_finite() && range.0 <= range.1) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("the range of the histogram is invalid: {:?}", range),
+            ));
+        }
+        if dataset.is_empty() || bins == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the statistics need at least one image and one bin",
+            ));
+        }
+        let pixels = rows * cols;
+        let mut sum = Array1::<f64>::zeros(pixels);
+        let mut sum_sq = Array1::<f64>::zeros(pixels);
+        let mut class_sums: BTreeMap<u8, Array1<f64>> = BTreeMap::new();
+        let mut class_counts: BTreeMap<u8, usize> = BTreeMap::new();
+        let histogram_range = range;
+        let width = (range.1 - range.0) / bins as f64;
+        let mut histogram = vec![0; bins];
+        let mut range = (f64::INFINITY, f64::NEG_INFINITY);
+        for i in 0..dataset.len() {
+            let item = dataset.get(i);
+            if item.image.len() != pixels {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "the image {} has {} pixels, but {} are expected",
+                        i,
+                        item.image.len(),
+                        pixels
+                    ),
+                ));
+            }
+            let image = item.image.index_axis(Axis(0), 0);
+            sum += &image;
+            sum_sq += &image.mapv(|x| x * x);
+            *class_sums
+                .entry(item.label)
+                .or_insert_with(|| Array1::zeros(pixels)) += &image;
+            *class_counts.entry(item.label).or_insert(0) += 1;
+            for &x in image.iter() {
+                range = (range.0.min(x), range.1.max(x));
+                let bin = if width > 0. {
+                    ((x - histogram_range.0) / width)
+                        .max(0.)
+                        .min((bins - 1) as f64) as usize
+                } else {
+                    0
+                };
+                histogram[bin] += 1;
+            }
+        }
+
+        let n = dataset.len() as f64;
+        let mean = sum / n;
+        let std = (&sum_sq / n - mean.mapv(|x| x * x)).mapv(|x| x.max(0.).sqrt());
+        let global_mean = mean.mean().unwrap();
+        let global_std = (sum_sq.sum() / (n * pixels as f64) - global_mean * global_mean)
+            .max(0.)
+            .sqrt();
+        let class_means = class_sums
+            .into_iter()
+            .map(|(label, s)| (label, s / class_counts[&label] as f64))
+            .collect();
+        Ok(Self {
+            len: dataset.len(),
+            rows,
+            cols,
+            class_counts,
+            mean,
+            std,
+            global_mean,
+            global_std,
+            range,
+            histogram_range,
+            histogram,
+            class_means,
+        })
+    }
+
+    /// `imbalance` returns the ratio of the largest class to the smallest class
+    pub fn imbalance(&self) -> f64 {
+        let max = self.class_counts.values().max().cloned().unwrap_or(0);
+        let min = self.class_counts.values().min().cloned().unwrap_or(0);
+        max as f64 / min.max(1) as f64
+    }
+
+    /// `save_mean_images` writes the mean image of each class to `dir` as `class_<label>.png`.
+    /// The pixels are scaled from `range` to 0-255.
+    /// It returns the paths of the written files.
+    pub fn save_mean_images<P: AsRef<Path>>(&self, dir: P) -> io::Result<vec::Vec<PathBuf>> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let (lo, hi) = self.range;
+        let scale = if hi > lo { 255. / (hi - lo) } else { 0. };
+        self.class_means
+            .iter()
+            .map(|(label, mean)| {
+                let image = ImageBuffer::from_fn(self.cols as u32, self.rows as u32, |x, y| {
+                    let v = mean[y as usize * self.cols + x as usize];
+                    Luma([((v - lo) * scale).round().max(0.).min(255.) as u8])
+                });
+                let path = dir.join(format!("class_{}.png", label));
+                to_io(image.save(&path), io::ErrorKind::Other).map(|_| path)
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for DatasetStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "images: {} ({}x{}), classes: {}, imbalance (max/min): {:.2}",
+            self.len,
+            self.rows,
+            self.cols,
+            self.class_counts.len(),
+            self.imbalance()
+        )?;
+        for (label, count) in self.class_counts.iter() {
+            writeln!(
+                f,
+                "    class {:>3}: {:>8} ({:5.2}%)",
+                label,
+                count,
+                100. * *count as f64 / self.len as f64
+            )?;
+        }
+        writeln!(
+            f,
+            "pixels: mean {:.4}, std {:.4}, min {}, max {}",
+            self.global_mean, self.global_std, self.range.0, self.range.1
+        )?;
+        let (lo, hi) = self.histogram_range;
+        let width = (hi - lo) / self.histogram.len() as f64;
+        let max = self.histogram.iter().max().cloned().unwrap_or(0).max(1);
+        for (i, &count) in self.histogram.iter().enumerate() {
+            writeln!(
+                f,
+                "    [{:>10.4}, {:>10.4}) {:>10} {}",
+                lo + width * i as f64,
+                lo + width * (i + 1) as f64,
+                count,
+                "#".repeat(count * 40 / max)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::arr2;
+    use std::cell::Cell;
+
+    // The dataset which counts the images taken
+    struct Counting(Vec<MnistImage>, Cell<usize>);
+
+    impl Dataset for Counting {
+        type Item = MnistImage;
+
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+
+        fn get(&self, index: usize) -> Self::Item {
+            self.1.set(self.1.get() + 1);
+            self.0[index].clone()
+        }
+    }
+
+    fn dataset() -> Vec<MnistImage> {
+        [
+            ([0., 0., 0., 4.], 1),
+            ([0., 2., 0., 4.], 1),
+            ([4., 4., 4., 4.], 0),
+        ]
+        .iter()
+        .map(|&(pixels, label)| MnistImage {
+            image: arr2(&[pixels]),
+            label,
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = DatasetStats::compute(&dataset(), 2, 2, 4).unwrap();
+        assert_eq!(
+            stats.class_counts.values().cloned().collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(stats.imbalance(), 2.);
+        assert!(stats
+            .mean
+            .iter()
+            .zip([4. / 3., 2., 4. / 3., 4.].iter())
+            .all(|(x, y)| (x - y).abs() < 1e-12));
+        assert_eq!(stats.std[3], 0.);
+        assert_eq!(stats.range, (0., 4.));
+        assert!((stats.global_mean - 13. / 6.).abs() < 1e-12);
+        assert_eq!(stats.histogram, vec![5, 0, 1, 6]);
+        assert_eq!(stats.class_means[&1].to_vec(), vec![0., 1., 0., 4.]);
+        assert!(stats.to_string().contains("class   1:        2"));
+        assert!(DatasetStats::compute(&dataset(), 3, 3, 4).is_err());
+
+        // Each image is decoded only once if the range is known (e.g. `ImageFolder`).
+        let counting = Counting(dataset(), Cell::new(0));
+        let once = DatasetStats::compute_with_range(&counting, 2, 2, 4, (0., 4.)).unwrap();
+        assert_eq!(
+            (counting.1.get(), once.histogram),
+            (3, stats.histogram.clone())
+        );
+        let wider = DatasetStats::compute_with_range(&dataset(), 2, 2, 2, (-4., 4.)).unwrap();
+        assert_eq!((wider.range, wider.histogram), ((0., 4.), vec![0, 12]));
+        assert!(DatasetStats::compute_with_range(&dataset(), 2, 2, 2, (1., 0.)).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let paths = stats.save_mean_images(dir.path()).unwrap();
+        assert_eq!(
+            paths,
+            vec![
+                dir.path().join("class_0.png"),
+                dir.path().join("class_1.png")
+            ]
+        );
+        let image = image::open(&paths[1]).unwrap().to_luma();
+        assert_eq!(image.into_raw(), vec![0, 64, 0, 255]);
+    }
+
+    #[test]
+    fn test_histogram_of_continuous_values() {
+        // The normalized or augmented pixels have few repeated values.
+        let data = (0..100)
+            .map(|i| MnistImage {
+                image: arr2(&[[
+                    i as f64 / 100. - 0.5,
+                    (i as f64 * 0.37).sin(),
+                    1. / (i as f64 + 1.),
+                    -(i as f64) / 1e3,
+                ]]),
+                label: (i % 3) as u8,
+            })
+            .collect::<Vec<_>>();
+        let stats = DatasetStats::compute(&data, 2, 2, 5).unwrap();
+        let (lo, hi) = stats.range;
+        assert_eq!(stats.histogram_range, stats.range);
+        assert_eq!(stats.histogram.iter().sum::<usize>(), 400);
+
+        let width = (hi - lo) / 5.;
+        let mut expected = vec![0; 5];
+        for x in data.iter().flat_map(|d| d.image.iter()) {
+            expected[(((x - lo) / width) as usize).min(4)] += 1;
+        }
+        assert_eq!(stats.histogram, expected);
+        assert!(stats.histogram.iter().all(|&c| c > 0));
+    }
+}
@@ -0,0 +1,435 @@
+use super::super::utils::fetch_client::{FetchClient, HashAlgorithm};
+use super::data_loader::Dataset;
+use super::mnist::{MnistImage, MnistTensor};
+use memmap2::Mmap;
+use ndarray::{ArrayD, ArrayView1, ArrayView2, Axis};
+use std::convert::TryInto;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// The directory of the cache files in the dataset directory
+pub const CACHE_DIR: &str = ".decoded";
+
+/// The extension of the cache files
+pub const CACHE_EXTENSION: &str = "tensor";
+
+// The magic number including the version of the format.
+const MAGIC: &[u8; 8] = b"DLPTNSR2";
+// The length of the key, which is the hex SHA-256.
+const KEY_LEN: usize = 64;
+// magic, key, N, rows and columns
+const HEADER_LEN: usize = MAGIC.len() + KEY_LEN + 3 * 8;
+
+/// `cache_key` computes the key of the decoded dataset.
+/// The key changes whenever the source archives (identified by their digests),
+/// the decoded files or the settings of decoding (e.g. transposition) change.
+/// The normalization is not a part of the key since it is applied when the cache is loaded.
+///
+/// # Arguments
+///
+/// * `client` - The client of the source archives
+/// * `fnames` - The names of the decoded files
+/// * `settings` - Description of the settings of decoding
+pub fn cache_key(client: &FetchClient, fnames: &[&str], settings: &str) -> String {
+    let mut sources = client
+        .dir_client
+        .file
+        .iter()
+        .map(|(fname, info)| format!("{}={}", fname, info.digest))
+        .collect::<Vec<_>>();
+    sources.sort_unstable();
+    let key = format!("{}|{}|{}", sources.join(","), fnames.join(","), settings);
+    HashAlgorithm::Sha256.hex_digest(key.as_bytes())
+}
+
+/// `cache_path` returns the path of the cache file of `key` in the directory of `client`
+pub fn cache_path(client: &FetchClient, key: &str) -> PathBuf {
+    client
+        .dir_client
+        .path()
+        .join(CACHE_DIR)
+        .join(format!("{}.{}", key, CACHE_EXTENSION))
+}
+
+/// `load` maps the decoded dataset cached in the directory of `client`.
+/// It returns `None` if it is not cached yet or the cache is broken.
+///
+/// # Arguments
+///
+/// * `client` - The client of the source archives
+/// * `fnames` - The names of the decoded files
+/// * `settings` - Description of the settings of decoding
+pub fn load(client: &FetchClient, fnames: &[&str], settings: &str) -> Option<MappedTensor> {
+    let key = cache_key(client, fnames, settings);
+    MappedTensor::open(&cache_path(client, &key), &key).ok()
+}
+
+/// `store` saves the decoded images and labels to the directory of `client` for `load`,
+/// and returns the path of the cache file.
+/// The file is written while holding the lock of the directory,
+/// so the processes sharing the directory do not write the same file at the same time.
+///
+/// # Arguments
+///
+/// * `client` - The client of the source archives
+/// * `fnames` - The names of the decoded files
+/// * `settings` - Description of the settings of decoding
+/// * `images` - The raw images of shape (N, rows, columns)
+/// * `labels` - The labels of shape (N)
+pub fn store(
+    client: &FetchClient,
+    fnames: &[&str],
+    settings: &str,
+    images: &ArrayD<u8>,
+    labels: &ArrayD<u8>,
+) -> io::Result<PathBuf> {
+    let key = cache_key(client, fnames, settings);
+    let path = cache_path(client, &key);
+    let _lock = client.dir_client.lock(client.lock_timeout())?;
+    save(&path, &key, images, labels)?;
+    Ok(path)
+}
+
+/// `load_or_build` loads the decoded dataset cached in the directory of `client`.
+/// If it is not cached yet or the cache is broken, it calls `build`
+/// and saves the result for the next time.
+/// A failure to save the cache (e.g. a read-only directory) is reported to
+/// `Progress::cache_failed` and is not an error.
+///
+/// # Arguments
+///
+/// * `client` - The client of the source archives
+/// * `fnames` - The names of the decoded files
+/// * `settings` - Description of the settings of decoding
+/// * `normalize` - Flag that determines whether the image is normalized between 0.0 and 1.0.
+/// * `build` - Decodes the raw images and labels from the source archives
+pub fn load_or_build<F>(
+    client: &FetchClient,
+    fnames: &[&str],
+    settings: &str,
+    normalize: bool,
+    build: F,
+) -> io::Result<MnistTensor>
+where
+    F: FnOnce() -> io::Result<(ArrayD<u8>, ArrayD<u8>)>,
+{
+    if let Some(mapped) = load(client, fnames, settings) {
+        return Ok(mapped.normalize(normalize).to_tensor());
+    }
+    let (images, labels) = build()?;
+    store_or_report(client, fnames, settings, &images, &labels);
+    MnistTensor::from_idx(images, labels, normalize)
+}
+
+/// `load_mapped_or_build` is the same as `load_or_build`
+/// except that it returns the mapped cache instead of copying it into memory.
+/// A failure to save the cache is an error since there is nothing to map.
+pub fn load_mapped_or_build<F>(
+    client: &FetchClient,
+    fnames: &[&str],
+    settings: &str,
+    build: F,
+) -> io::Result<MappedTensor>
+where
+    F: FnOnce() -> io::Result<(ArrayD<u8>, ArrayD<u8>)>,
+{
+    if let Some(mapped) = load(client, fnames, settings) {
+        return Ok(mapped);
+    }
+    let (images, labels) = build()?;
+    let path = store(client, fnames, settings, &images, &labels)?;
+    MappedTensor::open(&path, &cache_key(client, fnames, settings))
+}
+
+/// `store_or_report` is the same as `store`
+/// except that the failure is reported to `Progress::cache_failed` instead of returned
+pub fn store_or_report(
+    client: &FetchClient,
+    fnames: &[&str],
+    settings: &str,
+    images: &ArrayD<u8>,
+    labels: &ArrayD<u8>,
+) {
+    if let Err(e) = store(client, fnames, settings, images, labels) {
+        let path = cache_path(client, &cache_key(client, fnames, settings));
+        client.progress().cache_failed(&path.to_string_lossy(), &e);
+    }
+}
+
+/// `save` writes the raw images and labels to `path` in the format of the cache.
+/// The file is written to a temporary file and renamed,
+/// so the readers never see the partially written file.
+///
+/// The format is the following, where all the integers are little-endian.
+///
+/// | offset | size | description |
+/// |--------|------|-------------|
+/// | 0 | 8 | magic number `DLPTNSR2` |
+/// | 8 | 64 | key (see `cache_key`) |
+/// | 72 | 8 | the number of images N |
+/// | 80 | 8 | rows |
+/// | 88 | 8 | columns |
+/// | 96 | N * rows * columns | images (`u8`) |
+/// | 96 + N * rows * columns | N | labels (`u8`) |
+pub fn save(path: &Path, key: &str, images: &ArrayD<u8>, labels: &ArrayD<u8>) -> io::Result<()> {
+    if key.len() != KEY_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("the key must have {} bytes", KEY_LEN),
+        ));
+    }
+    let (n, rows, cols) = match *images.shape() {
+        [n, rows, cols] if labels.shape() == [n] => (n, rows, cols),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "the images {:?} and labels {:?} are not in MNIST layout",
+                    images.shape(),
+                    labels.shape()
+                ),
+            ))
+        }
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let part = path.with_extension("part");
+    {
+        let mut w = io::BufWriter::new(fs::File::create(&part)?);
+        w.write_all(MAGIC)?;
+        w.write_all(key.as_bytes())?;
+        for &x in [n, rows, cols].iter() {
+            w.write_all(&(x as u64).to_le_bytes())?;
+        }
+        for a in [images, labels].iter() {
+            match a.as_slice() {
+                Some(bytes) => w.write_all(bytes)?,
+                None => w.write_all(&a.iter().cloned().collect::<Vec<_>>())?,
+            }
+        }
+        w.flush()?;
+    }
+    fs::rename(&part, path)
+}
+
+/// `MappedTensor` is the cache file mapped to memory.
+/// The images and labels are accessed as views without reading the whole file,
+/// and it is a `Dataset` which converts only the accessed images to `f64`.
+pub struct MappedTensor {
+    mmap: Mmap,
+    len: usize,
+    rows: usize,
+    cols: usize,
+    normalize: bool,
+}
+
+impl MappedTensor {
+    /// `open` maps the cache file at `path`.
+    /// It returns `Err` if the file is not a valid cache file of `key`.
+    pub fn open(path: &Path, key: &str) -> io::Result<Self> {
+        let file = fs::File::open(path)?;
+        // The cache files are replaced by renaming and never modified in place.
+        let mmap = unsafe { Mmap::map(&file)? };
+        let invalid = |msg: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), msg),
+            )
+        };
+        if mmap.len() < HEADER_LEN || &mmap[..MAGIC.len()] != MAGIC {
+            return Err(invalid("not a cache file"));
+        }
+        if &mmap[MAGIC.len()..MAGIC.len() + KEY_LEN] != key.as_bytes() {
+            return Err(invalid("the key does not match"));
+        }
+        let field = |i: usize| {
+            let start = MAGIC.len() + KEY_LEN + 8 * i;
+            u64::from_le_bytes(mmap[start..start + 8].try_into().unwrap()) as usize
+        };
+        let (len, rows, cols) = (field(0), field(1), field(2));
+        let expected = len
+            .checked_mul(rows)
+            .and_then(|x| x.checked_mul(cols))
+            .and_then(|x| x.checked_add(len))
+            .and_then(|x| x.checked_add(HEADER_LEN));
+        if expected != Some(mmap.len()) {
+            return Err(invalid("the file is truncated"));
+        }
+        Ok(Self {
+            mmap,
+            len,
+            rows,
+            cols,
+            normalize: false,
+        })
+    }
+
+    /// `normalize` sets whether the pixels converted by `to_tensor` and `Dataset::get`
+    /// are normalized between 0.0 and 1.0 (false by default)
+    pub fn normalize(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    /// `shape` returns the rows and columns of an image
+    pub fn shape(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    /// `images` returns the view of the raw images of shape (N, rows * columns)
+    pub fn images(&self) -> ArrayView2<'_, u8> {
+        let size = self.len * self.rows * self.cols;
+        ArrayView2::from_shape(
+            (self.len, self.rows * self.cols),
+            &self.mmap[HEADER_LEN..HEADER_LEN + size],
+        )
+        .unwrap()
+    }
+
+    /// `labels` returns the view of the labels of shape (N)
+    pub fn labels(&self) -> ArrayView1<'_, u8> {
+        let start = HEADER_LEN + self.len * self.rows * self.cols;
+        ArrayView1::from(&self.mmap[start..start + self.len])
+    }
+
+    /// `to_tensor` converts the mapped data into `MnistTensor`
+    pub fn to_tensor(&self) -> MnistTensor {
+        let scale = self.scale();
+        MnistTensor {
+            images: self.images().mapv(|x| f64::from(x) / scale),
+            labels: self.labels().to_owned(),
+            rows: self.rows,
+            cols: self.cols,
+        }
+    }
+
+    fn scale(&self) -> f64 {
+        if self.normalize {
+            255.
+        } else {
+            1.
+        }
+    }
+}
+
+impl Dataset for MappedTensor {
+    type Item = MnistImage;
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn get(&self, index: usize) -> Self::Item {
+        let scale = self.scale();
+        MnistImage {
+            image: self
+                .images()
+                .row(index)
+                .insert_axis(Axis(0))
+                .mapv(|x| f64::from(x) / scale),
+            label: self.labels()[index],
+        }
+    }
+
+    fn label(&self, index: usize) -> Option<usize> {
+        Some(self.labels()[index] as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::utils::fetch_client::{
+        CacheRoot, ClientOptions, FConf, Progress, RemoteFile,
+    };
+    use super::*;
+    use ndarray::{arr1, IxDyn};
+    use std::cell::Cell;
+    use std::sync::{Arc, Mutex};
+
+    fn raw() -> (ArrayD<u8>, ArrayD<u8>) {
+        (
+            ArrayD::from_shape_vec(IxDyn(&[3, 2, 2]), (0..12).map(|x| x * 20).collect()).unwrap(),
+            arr1(&[7, 2, 1]).into_dyn(),
+        )
+    }
+
+    #[derive(Default)]
+    struct Failures(Mutex<Vec<String>>);
+
+    impl Progress for Failures {
+        fn cache_failed(&self, fname: &str, _error: &io::Error) {
+            self.0.lock().unwrap().push(fname.to_string());
+        }
+    }
+
+    #[test]
+    fn test_save_and_map() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CACHE_DIR).join("a.tensor");
+        let key = HashAlgorithm::Sha256.hex_digest(b"key");
+        let (images, labels) = raw();
+        save(&path, &key, &images, &labels).unwrap();
+
+        let mapped = MappedTensor::open(&path, &key).unwrap();
+        assert_eq!((mapped.len(), mapped.shape()), (3, (2, 2)));
+        assert_eq!(mapped.images().row(1).to_vec(), vec![80, 100, 120, 140]);
+        assert_eq!(mapped.labels(), arr1(&[7, 2, 1]));
+        let expected = MnistTensor::from_idx(images.clone(), labels.clone(), true).unwrap();
+        let mapped = mapped.normalize(true);
+        assert_eq!(mapped.to_tensor().images, expected.images);
+        assert_eq!(mapped.get(2).image.row(0), expected.images.row(2));
+
+        let other = HashAlgorithm::Sha256.hex_digest(b"other");
+        assert!(MappedTensor::open(&path, &other).is_err());
+        let data = fs::read(&path).unwrap();
+        fs::write(&path, &data[..data.len() - 1]).unwrap();
+        assert!(MappedTensor::open(&path, &key).is_err());
+        assert!(save(&path, &key, &images, &arr1(&[0]).into_dyn()).is_err());
+    }
+
+    #[test]
+    fn test_load_or_build() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = [RemoteFile::new("mem://fixtures/", "data.gz", "0123", "")];
+        let failures = Arc::new(Failures::default());
+        let opts = ClientOptions::new()
+            .progress(failures.clone())
+            .cache_root(CacheRoot::Dir(dir.path().to_path_buf()));
+        let client =
+            FetchClient::with_options(FConf::new(".fixtures", files.iter()), &opts).unwrap();
+
+        let builds = Cell::new(0);
+        let build = || {
+            builds.set(builds.get() + 1);
+            Ok(raw())
+        };
+        let first = load_or_build(&client, &["data.gz"], "", false, build).unwrap();
+        // The normalization is applied to the same cache.
+        let second = load_or_build(&client, &["data.gz"], "", true, build).unwrap();
+        assert_eq!(builds.get(), 1);
+        assert_eq!(first.images / 255., second.images);
+        let mapped = load_mapped_or_build(&client, &["data.gz"], "", build).unwrap();
+        assert_eq!((builds.get(), mapped.len()), (1, 3));
+        load_or_build(&client, &["data.gz"], "transpose=true", false, build).unwrap();
+        assert_eq!(builds.get(), 2);
+        assert!(failures.0.lock().unwrap().is_empty());
+
+        // The cache directory cannot be created where a file exists.
+        let dir = tempfile::tempdir().unwrap();
+        let opts = opts.cache_root(CacheRoot::Dir(dir.path().to_path_buf()));
+        let client =
+            FetchClient::with_options(FConf::new(".fixtures", files.iter()), &opts).unwrap();
+        fs::create_dir_all(client.dir_client.path()).unwrap();
+        fs::write(client.dir_client.file_path(CACHE_DIR), b"").unwrap();
+        assert_eq!(
+            load_or_build(&client, &["data.gz"], "", false, build)
+                .unwrap()
+                .len(),
+            3
+        );
+        assert_eq!(failures.0.lock().unwrap().len(), 1);
+        assert!(load_mapped_or_build(&client, &["data.gz"], "", build).is_err());
+    }
+}
@@ -0,0 +1,304 @@
+use super::data_loader::Dataset;
+use super::mnist::MnistImage;
+use super::random::standard_normal;
+use ndarray::{Array1, Array2, Axis};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f64::consts::PI;
+use std::io;
+use std::vec;
+
+/// `ToyClassification` is a generated classification problem of 2-D points.
+/// It is a `Dataset` whose items are the points as images of shape (1, 2),
+/// so it works with `DataLoader` and `split` in the same way as MNIST.
+#[derive(Debug, Clone)]
+pub struct ToyClassification {
+    /// Points of shape (N, 2)
+    pub features: Array2<f64>,
+    /// Labels of shape (N)
+    pub labels: Array1<u8>,
+}
+
+impl ToyClassification {
+    fn from_points(points: vec::Vec<(f64, f64, u8)>) -> Self {
+        let n = points.len();
+        Self {
+            features: Array2::from_shape_vec(
+                (n, 2),
+                points.iter().flat_map(|p| vec![p.0, p.1]).collect(),
+            )
+            .unwrap(),
+            labels: points.iter().map(|p| p.2).collect(),
+        }
+    }
+
+    /// `classes` returns the number of classes
+    pub fn classes(&self) -> usize {
+        self.labels.iter().max().map_or(0, |&l| l as usize + 1)
+    }
+}
+
+impl Dataset for ToyClassification {
+    type Item = MnistImage;
+
+    fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    fn get(&self, index: usize) -> Self::Item {
+        MnistImage {
+            image: self.features.row(index).insert_axis(Axis(0)).to_owned(),
+            label: self.labels[index],
+        }
+    }
+
+    fn label(&self, index: usize) -> Option<usize> {
+        Some(self.labels[index] as usize)
+    }
+}
+
+/// `ToyRegression` is a generated regression problem
+#[derive(Debug, Clone)]
+pub struct ToyRegression {
+    /// Inputs of shape (N, D)
+    pub features: Array2<f64>,
+    /// Targets of shape (N)
+    pub targets: Array1<f64>,
+}
+
+/// `spirals` generates the points on the interleaved spirals, one per class.
+///
+/// # Arguments
+///
+/// * `n_per_class` - The number of points of each class
+/// * `classes` - The number of spirals between 1 and 256
+/// * `noise` - The standard deviation of the Gaussian noise added to the angles
+/// * `seed` - The seed of the random number generator
+pub fn spirals(
+    n_per_class: usize,
+    classes: usize,
+    noise: f64,
+    seed: u64,
+) -> io::Result<ToyClassification> {
+    check_classes(classes)?;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut points = Vec::with_capacity(n_per_class * classes);
+    for c in 0..classes {
+        for i in 0..n_per_class {
+            let r = i as f64 / n_per_class.max(2).saturating_sub(1) as f64;
+            let t =
+                4. * r + 2. * PI * c as f64 / classes as f64 + noise * standard_normal(&mut rng);
+            points.push((r * t.sin(), r * t.cos(), c as u8));
+        }
+    }
+    Ok(ToyClassification::from_points(points))
+}
+
+/// `moons` generates two interleaving half circles.
+/// Half of the points are the upper moon of label 0 and the others are the lower moon of label 1.
+///
+/// # Arguments
+///
+/// * `n` - The number of points
+/// * `noise` - The standard deviation of the Gaussian noise added to the points
+/// * `seed` - The seed of the random number generator
+pub fn moons(n: usize, noise: f64, seed: u64) -> ToyClassification {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let n_upper = n - n / 2;
+    let points = (0..n)
+        .map(|i| {
+            let (x, y, label) = if i < n_upper {
+                let t = PI * i as f64 / n_upper.max(2).saturating_sub(1) as f64;
+                (t.cos(), t.sin(), 0)
+            } else {
+                let t = PI * (i - n_upper) as f64 / (n / 2).max(2).saturating_sub(1) as f64;
+                (1. - t.cos(), 0.5 - t.sin(), 1)
+            };
+            (
+                x + noise * standard_normal(&mut rng),
+                y + noise * standard_normal(&mut rng),
+                label,
+            )
+        })
+        .collect();
+    ToyClassification::from_points(points)
+}
+
+/// `circles` generates two concentric circles.
+/// Half of the points are on the outer circle of radius 1 with label 0 and
+/// the others are on the inner circle of radius `factor` with label 1.
+///
+/// # Arguments
+///
+/// * `n` - The number of points
+/// * `factor` - The ratio of the inner radius to the outer one between 0 and 1
+/// * `noise` - The standard deviation of the Gaussian noise added to the points
+/// * `seed` - The seed of the random number generator
+pub fn circles(n: usize, factor: f64, noise: f64, seed: u64) -> io::Result<ToyClassification> {
+    if !(0. ..1.).contains(&factor) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("The factor must be in [0, 1), but {}", factor),
+        ));
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let n_outer = n - n / 2;
+    let points = (0..n)
+        .map(|i| {
+            let (k, m, r, label) = if i < n_outer {
+                (i, n_outer, 1., 0)
+            } else {
+                (i - n_outer, n / 2, factor, 1)
+            };
+            let t = 2. * PI * k as f64 / m as f64;
+            (
+                r * t.cos() + noise * standard_normal(&mut rng),
+                r * t.sin() + noise * standard_normal(&mut rng),
+                label,
+            )
+        })
+        .collect();
+    Ok(ToyClassification::from_points(points))
+}
+
+/// `xor` generates the Gaussian blobs around the four corners (±1, ±1).
+/// The label is 1 if the signs of the coordinates differ, i.e. the exclusive or,
+/// which a single perceptron cannot separate.
+///
+/// # Arguments
+///
+/// * `n` - The number of points, distributed evenly among the corners
+/// * `std` - The standard deviation of the blobs
+/// * `seed` - The seed of the random number generator
+pub fn xor(n: usize, std: f64, seed: u64) -> ToyClassification {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let corners = [(-1., -1., 0), (1., 1., 0), (-1., 1., 1), (1., -1., 1)];
+    let points = (0..n)
+        .map(|i| {
+            let (x, y, label) = corners[i % corners.len()];
+            (
+                x + std * standard_normal(&mut rng),
+                y + std * standard_normal(&mut rng),
+                label,
+            )
+        })
+        .collect();
+    ToyClassification::from_points(points)
+}
+
+/// `gaussian_mixture` generates the isotropic Gaussian blobs whose label is the index of the center.
+///
+/// # Arguments
+///
+/// * `n_per_class` - The number of points of each blob
+/// * `centers` - The centers of the blobs. There must be 1 to 256 centers.
+/// * `std` - The standard deviation of the blobs
+/// * `seed` - The seed of the random number generator
+pub fn gaussian_mixture(
+    n_per_class: usize,
+    centers: &[(f64, f64)],
+    std: f64,
+    seed: u64,
+) -> io::Result<ToyClassification> {
+    check_classes(centers.len())?;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut points = Vec::with_capacity(n_per_class * centers.len());
+    for (c, &(x, y)) in centers.iter().enumerate() {
+        for _ in 0..n_per_class {
+            points.push((
+                x + std * standard_normal(&mut rng),
+                y + std * standard_normal(&mut rng),
+                c as u8,
+            ));
+        }
+    }
+    Ok(ToyClassification::from_points(points))
+}
+
+/// `linear_regression` generates \\(y=\boldsymbol{x}\cdot\boldsymbol{w}+b+\varepsilon\\)
+/// where \\(\boldsymbol{x}\\) is uniform in \\([-1,1]^D\\) and
+/// \\(\varepsilon\\) is the Gaussian noise.
+///
+/// # Arguments
+///
+/// * `n` - The number of samples
+/// * `weights` - The true weights \\(\boldsymbol{w}\\) of \\(D\\) dimensions
+/// * `bias` - The true bias \\(b\\)
+/// * `noise` - The standard deviation of \\(\varepsilon\\)
+/// * `seed` - The seed of the random number generator
+pub fn linear_regression(
+    n: usize,
+    weights: &[f64],
+    bias: f64,
+    noise: f64,
+    seed: u64,
+) -> ToyRegression {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let features = Array2::from_shape_fn((n, weights.len()), |_| rng.gen_range(-1., 1.));
+    let targets = features.dot(&Array1::from(weights.to_vec()))
+        + bias
+        + Array1::from_shape_fn(n, |_| noise * standard_normal(&mut rng));
+    ToyRegression { features, targets }
+}
+
+fn check_classes(classes: usize) -> io::Result<()> {
+    if classes == 0 || classes > u8::MAX as usize + 1 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "The number of classes must be between 1 and 256, but {}",
+                classes
+            ),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classification() {
+        let data = spirals(50, 3, 0.1, 7).unwrap();
+        assert_eq!((data.features.dim(), data.classes()), ((150, 2), 3));
+        assert_eq!(spirals(50, 3, 0.1, 7).unwrap().features, data.features);
+        assert_ne!(spirals(50, 3, 0.1, 8).unwrap().features, data.features);
+        assert!(spirals(1, 0, 0., 0).is_err());
+
+        let data = moons(101, 0., 0);
+        assert_eq!(data.labels.iter().filter(|&&l| l == 1).count(), 50);
+        assert!(data.features.row(0).to_vec() == vec![1., 0.]);
+
+        let data = circles(100, 0.5, 0., 0).unwrap();
+        for (p, &label) in data.features.outer_iter().zip(data.labels.iter()) {
+            let r = p.dot(&p).sqrt();
+            assert!((r - if label == 0 { 1. } else { 0.5 }).abs() < 1e-12);
+        }
+        assert!(circles(10, 1.5, 0., 0).is_err());
+
+        // The exclusive or is not linearly separable but the product of the coordinates separates it.
+        let data = xor(400, 0.1, 3);
+        for (p, &label) in data.features.outer_iter().zip(data.labels.iter()) {
+            assert_eq!(p[0] * p[1] < 0., label == 1);
+        }
+
+        let data = gaussian_mixture(100, &[(0., 0.), (5., 5.)], 0.5, 1).unwrap();
+        assert_eq!(data.len(), 200);
+        let item = data.get(150);
+        assert_eq!((item.image.dim(), item.label), ((1, 2), 1));
+        assert!((data.features.slice(ndarray::s![100.., 0]).mean().unwrap() - 5.).abs() < 0.2);
+    }
+
+    #[test]
+    fn test_linear_regression() {
+        let data = linear_regression(200, &[2., -3.], 0.5, 0., 4);
+        assert_eq!(data.features.dim(), (200, 2));
+        assert!(data.features.iter().all(|x| x.abs() <= 1.));
+        let x = data.features.row(10);
+        assert!((data.targets[10] - (2. * x[0] - 3. * x[1] + 0.5)).abs() < 1e-12);
+        let noisy = linear_regression(200, &[2., -3.], 0.5, 0.1, 4);
+        assert_eq!(noisy.features, data.features);
+        assert_ne!(noisy.targets, data.targets);
+    }
+}
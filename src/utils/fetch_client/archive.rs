@@ -0,0 +1,278 @@
+extern crate bzip2;
+extern crate libflate;
+extern crate tar;
+extern crate xz2;
+extern crate zip;
+
+use super::super::natural_transform::to_io;
+use libflate::gzip::Decoder;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// The kind of archive (or compressed file) which can be extracted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveKind {
+    /// Single file compressed by gzip (`.gz`)
+    Gzip,
+    /// Tape archive (`.tar`)
+    Tar,
+    /// Tape archive compressed by gzip (`.tar.gz`, `.tgz`)
+    TarGz,
+    /// Zip archive (`.zip`)
+    Zip,
+    /// Single file compressed by bzip2 (`.bz2`)
+    Bzip2,
+    /// Single file compressed by xz (`.xz`)
+    Xz,
+}
+
+impl ArchiveKind {
+    /// `from_file_name` deduces the kind of archive from the extension of `fname`
+    pub fn from_file_name(fname: &str) -> Option<Self> {
+        let lower = fname.to_lowercase();
+        if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        } else if lower.ends_with(".tar") {
+            Some(ArchiveKind::Tar)
+        } else if lower.ends_with(".gz") {
+            Some(ArchiveKind::Gzip)
+        } else if lower.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else if lower.ends_with(".bz2") {
+            Some(ArchiveKind::Bzip2)
+        } else if lower.ends_with(".xz") {
+            Some(ArchiveKind::Xz)
+        } else {
+            None
+        }
+    }
+
+    /// `from_magic` deduces the kind of archive from the leading bytes of the file.
+    /// A gzip file is always regarded as `ArchiveKind::Gzip`
+    /// since the magic number cannot tell whether it contains a tape archive.
+    pub fn from_magic(head: &[u8]) -> Option<Self> {
+        if head.starts_with(&[0x1f, 0x8b]) {
+            Some(ArchiveKind::Gzip)
+        } else if head.starts_with(b"PK\x03\x04") {
+            Some(ArchiveKind::Zip)
+        } else if head.starts_with(b"BZh") {
+            Some(ArchiveKind::Bzip2)
+        } else if head.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Some(ArchiveKind::Xz)
+        } else if head.len() >= 262 && &head[257..262] == b"ustar" {
+            Some(ArchiveKind::Tar)
+        } else {
+            None
+        }
+    }
+
+    /// `detect` deduces the kind of archive of the file at `path`
+    /// from its extension, and then from its magic number.
+    pub fn detect(path: &Path) -> io::Result<Option<Self>> {
+        if let Some(kind) = path.to_str().and_then(Self::from_file_name) {
+            return Ok(Some(kind));
+        }
+        let mut head = Vec::with_capacity(512);
+        File::open(path)?.take(512).read_to_end(&mut head)?;
+        Ok(Self::from_magic(&head))
+    }
+
+    /// `is_single_file` checks if the archive consists of one compressed stream
+    /// (gzip, bzip2 or xz) rather than multiple entries.
+    pub fn is_single_file(self) -> bool {
+        match self {
+            ArchiveKind::Gzip | ArchiveKind::Bzip2 | ArchiveKind::Xz => true,
+            ArchiveKind::Tar | ArchiveKind::TarGz | ArchiveKind::Zip => false,
+        }
+    }
+}
+
+fn unsupported(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{} is not a supported archive", path.display()),
+    )
+}
+
+/// The name of the file decompressed from the single file archive `path`,
+/// i.e. the file name without the last extension (`train-images-idx3-ubyte.gz` to `train-images-idx3-ubyte`).
+pub fn decompressed_name(path: &Path) -> io::Result<PathBuf> {
+    path.file_stem().map(PathBuf::from).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Cannot deduce the name of unarchived file from archived file",
+        )
+    })
+}
+
+/// `open_decoder` opens the single file archive (gzip, bzip2 or xz) at `path`
+/// and returns the reader of the decompressed stream.
+pub fn open_decoder(path: &Path, kind: ArchiveKind) -> io::Result<Box<dyn Read>> {
+    let src = BufReader::new(File::open(path)?);
+    match kind {
+        ArchiveKind::Gzip => Ok(Box::new(Decoder::new(src)?)),
+        ArchiveKind::Bzip2 => Ok(Box::new(bzip2::read::BzDecoder::new(src))),
+        ArchiveKind::Xz => Ok(Box::new(xz2::read::XzDecoder::new(src))),
+        _ => Err(unsupported(path)),
+    }
+}
+
+/// `decompress_to_vec` decompresses the single file archive at `path` into memory.
+/// The kind of archive is detected by `ArchiveKind::detect`.
+pub fn decompress_to_vec(path: &Path) -> io::Result<Vec<u8>> {
+    match ArchiveKind::detect(path)? {
+        Some(kind) if kind.is_single_file() => {
+            let mut buf = vec![];
+            open_decoder(path, kind)?.read_to_end(&mut buf)?;
+            Ok(buf)
+        }
+        _ => Err(unsupported(path)),
+    }
+}
+
+/// `extract` detects the kind of archive at `src` and unpacks it under `dst_dir`.
+/// It returns the paths of the extracted files.
+///
+/// # Arguments
+///
+/// * `src` - Path of the archive
+/// * `dst_dir` - The directory where the files are extracted
+pub fn extract(src: &Path, dst_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    match ArchiveKind::detect(src)? {
+        Some(kind) => extract_as(src, dst_dir, kind),
+        None => Err(unsupported(src)),
+    }
+}
+
+/// `extract_as` unpacks the archive at `src` under `dst_dir` as the specified kind.
+/// It returns the paths of the extracted files.
+pub fn extract_as(src: &Path, dst_dir: &Path, kind: ArchiveKind) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dst_dir)?;
+    match kind {
+        ArchiveKind::Gzip | ArchiveKind::Bzip2 | ArchiveKind::Xz => {
+            let dst = dst_dir.join(decompressed_name(src)?);
+            let mut out = File::create(&dst)?;
+            io::copy(&mut open_decoder(src, kind)?, &mut out)?;
+            Ok(vec![dst])
+        }
+        ArchiveKind::Tar => unpack_tar(File::open(src)?, dst_dir),
+        ArchiveKind::TarGz => unpack_tar(Decoder::new(BufReader::new(File::open(src)?))?, dst_dir),
+        ArchiveKind::Zip => unpack_zip(File::open(src)?, dst_dir),
+    }
+}
+
+fn unpack_tar<R: Read>(src: R, dst_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut archive = tar::Archive::new(src);
+    let mut extracted = vec![];
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = dst_dir.join(entry.path()?);
+        // `unpack_in` refuses the entries which would be placed outside of `dst_dir`.
+        if entry.unpack_in(dst_dir)? && entry.header().entry_type().is_file() {
+            extracted.push(path);
+        }
+    }
+    Ok(extracted)
+}
+
+fn unpack_zip(src: File, dst_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut archive = to_io(zip::ZipArchive::new(src), io::ErrorKind::InvalidData)?;
+    let mut extracted = vec![];
+    for i in 0..archive.len() {
+        let mut file = to_io(archive.by_index(i), io::ErrorKind::InvalidData)?;
+        // Skip the entries which would be placed outside of `dst_dir`.
+        let path = match file.enclosed_name() {
+            Some(name) => dst_dir.join(name),
+            None => continue,
+        };
+        if file.is_dir() {
+            fs::create_dir_all(&path)?;
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            io::copy(&mut file, &mut File::create(&path)?)?;
+            extracted.push(path);
+        }
+    }
+    Ok(extracted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const CONTENT: &[u8] = b"deep learning playground";
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = libflate::gzip::Encoder::new(vec![]).unwrap();
+        encoder.write_all(data).unwrap();
+        encoder.finish().into_result().unwrap()
+    }
+
+    fn tar(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(vec![]);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, name, data).unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    fn zip(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(io::Cursor::new(vec![]));
+        writer
+            .start_file(name, zip::write::FileOptions::default())
+            .unwrap();
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn check_extract(fname: &str, archive: &[u8], expected: &str) {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join(fname);
+        fs::write(&src, archive).unwrap();
+
+        let dst = dir.path().join("out");
+        let extracted = extract(&src, &dst).unwrap();
+        assert_eq!(extracted, vec![dst.join(expected)]);
+        assert_eq!(fs::read(dst.join(expected)).unwrap(), CONTENT);
+    }
+
+    #[test]
+    fn test_extract() {
+        let mut bz2 = bzip2::write::BzEncoder::new(vec![], bzip2::Compression::Default);
+        bz2.write_all(CONTENT).unwrap();
+        let mut xz = xz2::write::XzEncoder::new(vec![], 6);
+        xz.write_all(CONTENT).unwrap();
+
+        check_extract("data.bin.gz", &gzip(CONTENT), "data.bin");
+        check_extract("data.tar", &tar("dir/data.bin", CONTENT), "dir/data.bin");
+        check_extract("data.tar.gz", &gzip(&tar("data.bin", CONTENT)), "data.bin");
+        check_extract("data.zip", &zip("dir/data.bin", CONTENT), "dir/data.bin");
+        check_extract("data.bin.bz2", &bz2.finish().unwrap(), "data.bin");
+        check_extract("data.bin.xz", &xz.finish().unwrap(), "data.bin");
+    }
+
+    #[test]
+    fn test_detect_by_magic() {
+        assert_eq!(
+            ArchiveKind::from_magic(&gzip(CONTENT)),
+            Some(ArchiveKind::Gzip)
+        );
+        assert_eq!(
+            ArchiveKind::from_magic(&tar("data.bin", CONTENT)),
+            Some(ArchiveKind::Tar)
+        );
+        assert_eq!(
+            ArchiveKind::from_magic(&zip("data.bin", CONTENT)),
+            Some(ArchiveKind::Zip)
+        );
+        assert_eq!(ArchiveKind::from_magic(CONTENT), None);
+    }
+}
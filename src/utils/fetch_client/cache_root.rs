@@ -0,0 +1,129 @@
+extern crate dirs;
+
+use std::env;
+use std::io;
+use std::path::PathBuf;
+
+/// The environment variable that specifies the cache root directory
+pub const CACHE_DIR_ENV: &str = "DLP_CACHE_DIR";
+
+/// The name of the directory created under the user cache directory
+pub const CACHE_DIR_NAME: &str = "deep_learning_playground";
+
+/// `CacheRoot` decides the directory under which the directory of each dataset is created.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CacheRoot {
+    /// Resolve the root in the following order:
+    ///
+    /// 1. The directory specified by the environment variable `DLP_CACHE_DIR`.
+    /// 2. `deep_learning_playground` under the user cache directory
+    ///    (e.g. `$XDG_CACHE_HOME` or `~/.cache` on Linux).
+    Auto,
+    /// The specified directory
+    Dir(PathBuf),
+    /// The current working directory.
+    /// This is the behavior of the old versions, so the name of the dataset directory is
+    /// used as it is (e.g. `.mnist`).
+    CurrentDir,
+}
+
+impl Default for CacheRoot {
+    fn default() -> Self {
+        CacheRoot::Auto
+    }
+}
+
+impl CacheRoot {
+    /// `resolve` returns the path of the cache root directory
+    pub fn resolve(&self) -> io::Result<PathBuf> {
+        match self {
+            CacheRoot::Dir(path) => Ok(path.clone()),
+            CacheRoot::CurrentDir => env::current_dir(),
+            CacheRoot::Auto => match env::var_os(CACHE_DIR_ENV) {
+                Some(path) if !path.is_empty() => Ok(PathBuf::from(path)),
+                _ => dirs::cache_dir()
+                    .map(|path| path.join(CACHE_DIR_NAME))
+                    .ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::NotFound,
+                            format!(
+                                "cannot find the user cache directory (set {} to specify it)",
+                                CACHE_DIR_ENV
+                            ),
+                        )
+                    }),
+            },
+        }
+    }
+
+    /// `dataset_dir` returns the path of the directory of the dataset.
+    /// The leading dots of `save_dir_name` are removed unless the root is `CacheRoot::CurrentDir`,
+    /// so that `.mnist` is saved as `mnist` in the shared cache directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `save_dir_name` - The name of the directory of the dataset.
+    pub fn dataset_dir(&self, save_dir_name: &str) -> io::Result<PathBuf> {
+        let root = self.resolve()?;
+        Ok(match self {
+            CacheRoot::CurrentDir => root.join(save_dir_name),
+            _ => root.join(save_dir_name.trim_start_matches('.')),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dir_and_current_dir() {
+        let root = CacheRoot::Dir(PathBuf::from("/tmp/dlp"));
+        assert_eq!(root.resolve().unwrap(), PathBuf::from("/tmp/dlp"));
+        assert_eq!(
+            root.dataset_dir(".mnist").unwrap(),
+            PathBuf::from("/tmp/dlp/mnist")
+        );
+        assert_eq!(
+            root.dataset_dir("..cifar10").unwrap(),
+            PathBuf::from("/tmp/dlp/cifar10")
+        );
+
+        // The old layout keeps the hidden directory in the current directory.
+        let cwd = env::current_dir().unwrap();
+        assert_eq!(CacheRoot::CurrentDir.resolve().unwrap(), cwd);
+        assert_eq!(
+            CacheRoot::CurrentDir.dataset_dir(".mnist").unwrap(),
+            cwd.join(".mnist")
+        );
+    }
+
+    // The environment is shared by the tests running in parallel,
+    // so all the cases reading `DLP_CACHE_DIR` are in this test.
+    #[test]
+    fn test_auto_resolution() {
+        assert_eq!(CacheRoot::default(), CacheRoot::Auto);
+        let saved = env::var_os(CACHE_DIR_ENV);
+
+        env::set_var(CACHE_DIR_ENV, "/tmp/dlp-env");
+        assert_eq!(
+            CacheRoot::Auto.resolve().unwrap(),
+            PathBuf::from("/tmp/dlp-env")
+        );
+        assert_eq!(
+            CacheRoot::Auto.dataset_dir(".mnist").unwrap(),
+            PathBuf::from("/tmp/dlp-env/mnist")
+        );
+
+        // The empty variable falls back to the user cache directory.
+        env::set_var(CACHE_DIR_ENV, "");
+        let fallback = dirs::cache_dir().map(|path| path.join(CACHE_DIR_NAME));
+        assert_eq!(CacheRoot::Auto.resolve().ok(), fallback);
+        env::remove_var(CACHE_DIR_ENV);
+        assert_eq!(CacheRoot::Auto.resolve().ok(), fallback);
+
+        if let Some(path) = saved {
+            env::set_var(CACHE_DIR_ENV, path);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+extern crate crypto;
+
+use crypto::blake2b::Blake2b;
+use crypto::digest::Digest;
+use crypto::md5::Md5;
+use crypto::sha1::Sha1;
+use crypto::sha2::{Sha256, Sha512};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+/// The hash algorithm used to verify the downloaded file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    /// MD5
+    Md5,
+    /// SHA-1
+    Sha1,
+    /// SHA-256
+    Sha256,
+    /// SHA-512
+    Sha512,
+    /// BLAKE2b with 512 bits digest
+    Blake2b,
+}
+
+impl Default for HashAlgorithm {
+    fn default() -> Self {
+        HashAlgorithm::Sha256
+    }
+}
+
+impl HashAlgorithm {
+    /// `name` returns the lowercase name of the algorithm
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgorithm::Md5 => "md5",
+            HashAlgorithm::Sha1 => "sha1",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha512 => "sha512",
+            HashAlgorithm::Blake2b => "blake2b",
+        }
+    }
+
+    /// `hex_digest` computes the digest of `data` as a lowercase hex string
+    pub fn hex_digest(self, data: &[u8]) -> String {
+        let mut hasher: Box<dyn Digest> = match self {
+            HashAlgorithm::Md5 => Box::new(Md5::new()),
+            HashAlgorithm::Sha1 => Box::new(Sha1::new()),
+            HashAlgorithm::Sha256 => Box::new(Sha256::new()),
+            HashAlgorithm::Sha512 => Box::new(Sha512::new()),
+            HashAlgorithm::Blake2b => Box::new(Blake2b::new(64)),
+        };
+        hasher.input(data);
+        hasher.result_str()
+    }
+
+    /// `verify` checks if the digest of `data` matches `expected`.
+    /// The hex strings are compared case-insensitively.
+    /// The error message names the expected and actual digests.
+    ///
+    /// # Arguments
+    ///
+    /// * `fname` - File name used in the error message
+    /// * `data` - Content of the file
+    /// * `expected` - Expected digest as a hex string
+    pub fn verify(self, fname: &str, data: &[u8], expected: &str) -> io::Result<()> {
+        let actual = self.hex_digest(data);
+        if actual.eq_ignore_ascii_case(expected.trim()) {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} digest of {} does not match (expected: {}, actual: {})",
+                    self, fname, expected, actual
+                ),
+            ))
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('-', "").as_str() {
+            "md5" => Ok(HashAlgorithm::Md5),
+            "sha1" => Ok(HashAlgorithm::Sha1),
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            "sha512" => Ok(HashAlgorithm::Sha512),
+            "blake2b" => Ok(HashAlgorithm::Blake2b),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown hash algorithm: {}", s),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_digest() {
+        let expected = [
+            (HashAlgorithm::Md5, "900150983cd24fb0d6963f7d28e17f72"),
+            (
+                HashAlgorithm::Sha1,
+                "a9993e364706816aba3e25717850c26c9cd0d89d",
+            ),
+            (
+                HashAlgorithm::Sha256,
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (
+                HashAlgorithm::Sha512,
+                "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+                 2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+            ),
+            (
+                HashAlgorithm::Blake2b,
+                "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+                 7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923",
+            ),
+        ];
+        for (algorithm, digest) in expected.iter() {
+            assert_eq!(algorithm.hex_digest(b"abc"), *digest);
+            assert!(algorithm
+                .verify("abc", b"abc", &digest.to_uppercase())
+                .is_ok());
+            assert_eq!(
+                algorithm.name().parse::<HashAlgorithm>().unwrap(),
+                *algorithm
+            );
+        }
+        assert_eq!(HashAlgorithm::default(), HashAlgorithm::Sha256);
+    }
+
+    #[test]
+    fn test_verify_reports_digests() {
+        let err = HashAlgorithm::Md5
+            .verify("abc", b"abc", "0123")
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "md5 digest of abc does not match (expected: 0123, actual: 900150983cd24fb0d6963f7d28e17f72)"
+        );
+    }
+}
@@ -0,0 +1,90 @@
+extern crate fs2;
+
+use fs2::FileExt;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The name of the lock file created in the directory of the dataset
+pub const LOCK_FILE_NAME: &str = ".lock";
+
+/// The default time to wait for the lock held by another process
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(600);
+
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// `CacheLock` is the advisory lock of the directory of the dataset,
+/// which lets the processes sharing the directory download and extract the files one at a time.
+/// The lock is released when it is dropped.
+#[derive(Debug)]
+pub struct CacheLock {
+    file: File,
+}
+
+impl CacheLock {
+    /// `acquire` takes the exclusive lock of the lock file at `path`,
+    /// waiting up to `timeout` while another process holds it.
+    /// The waiting blocks the current thread; use `acquire_async` in an async context.
+    pub fn acquire(path: &Path, timeout: Duration) -> io::Result<Self> {
+        let file = open(path)?;
+        let start = Instant::now();
+        while !try_lock(&file, path, start, timeout)? {
+            thread::sleep(RETRY_INTERVAL);
+        }
+        Ok(Self { file })
+    }
+
+    /// `acquire_async` is the same as `acquire`
+    /// except that it waits on the timer of the runtime instead of blocking the current thread.
+    pub async fn acquire_async(path: &Path, timeout: Duration) -> io::Result<Self> {
+        let file = open(path)?;
+        let start = Instant::now();
+        while !try_lock(&file, path, start, timeout)? {
+            tokio::time::delay_for(RETRY_INTERVAL).await;
+        }
+        Ok(Self { file })
+    }
+}
+
+impl Drop for CacheLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+fn is_contended(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::WouldBlock
+        || e.raw_os_error() == fs2::lock_contended_error().raw_os_error()
+}
+
+fn open(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+}
+
+// It returns false while another process holds the lock and `timeout` has not elapsed.
+fn try_lock(file: &File, path: &Path, start: Instant, timeout: Duration) -> io::Result<bool> {
+    match file.try_lock_exclusive() {
+        Ok(()) => Ok(true),
+        Err(ref e) if is_contended(e) => {
+            if start.elapsed() >= timeout {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!(
+                        "timed out after {:?} waiting for the lock {}",
+                        timeout,
+                        path.display()
+                    ),
+                ));
+            }
+            Ok(false)
+        }
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,115 @@
+extern crate serde_json;
+extern crate toml;
+
+use super::super::natural_transform::to_io;
+use super::RemoteFileBuf;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// `Manifest` describes the set of files that makes up a dataset.
+/// It can be written in TOML or JSON, e.g.
+///
+/// ```toml
+/// save_dir = "my_dataset"
+///
+/// [[files]]
+/// host_and_path = "https://example.com/datasets/"
+/// fname = "images.tar.gz"
+/// digest = "..."
+/// algorithm = "md5"   # optional. md5, sha1, sha256 (default), sha512 or blake2b
+/// query = ""          # optional
+/// archive = "tar_gz"  # optional. gzip, tar, tar_gz, zip, bzip2 or xz
+/// ```
+///
+/// `FetchClient::from_manifest` constructs the client for the dataset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// The name of the directory of the dataset
+    pub save_dir: String,
+    /// Information of the files to be fetched
+    pub files: Vec<RemoteFileBuf>,
+}
+
+impl Manifest {
+    /// `Manifest` constructor
+    pub fn new<S: Into<String>>(save_dir: S, files: Vec<RemoteFileBuf>) -> Self {
+        Self {
+            save_dir: save_dir.into(),
+            files,
+        }
+    }
+
+    /// `from_toml_str` parses the manifest written in TOML
+    pub fn from_toml_str(s: &str) -> io::Result<Self> {
+        to_io(toml::from_str(s), io::ErrorKind::InvalidData)
+    }
+
+    /// `from_json_str` parses the manifest written in JSON
+    pub fn from_json_str(s: &str) -> io::Result<Self> {
+        to_io(serde_json::from_str(s), io::ErrorKind::InvalidData)
+    }
+
+    /// `from_path` reads the manifest file.
+    /// The file is parsed as JSON if its extension is `.json`, otherwise as TOML.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let s = fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json_str(&s),
+            _ => Self::from_toml_str(&s),
+        }
+    }
+
+    /// `to_toml_string` serializes the manifest to TOML
+    pub fn to_toml_string(&self) -> io::Result<String> {
+        to_io(toml::to_string(self), io::ErrorKind::InvalidData)
+    }
+
+    /// `to_json_string` serializes the manifest to JSON
+    pub fn to_json_string(&self) -> io::Result<String> {
+        to_io(
+            serde_json::to_string_pretty(self),
+            io::ErrorKind::InvalidData,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{ArchiveKind, HashAlgorithm};
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest() {
+        let from_toml = Manifest::from_toml_str(
+            r#"
+            save_dir = "dataset"
+
+            [[files]]
+            host_and_path = "https://example.com/"
+            fname = "data.tar.gz"
+            digest = "0123"
+            algorithm = "md5"
+            archive = "tar_gz"
+
+            [[files]]
+            host_and_path = "https://example.com/"
+            fname = "labels.bin"
+            sha256 = "4567"
+            query = "raw=true"
+            "#,
+        )
+        .unwrap();
+
+        let mut data = RemoteFileBuf::new("https://example.com/", "data.tar.gz", "0123", "");
+        data.algorithm = HashAlgorithm::Md5;
+        data.archive = Some(ArchiveKind::TarGz);
+        let labels = RemoteFileBuf::new("https://example.com/", "labels.bin", "4567", "raw=true");
+        assert_eq!(from_toml, Manifest::new("dataset", vec![data, labels]));
+
+        let from_json = Manifest::from_json_str(&from_toml.to_json_string().unwrap()).unwrap();
+        assert_eq!(from_json, from_toml);
+    }
+}
@@ -0,0 +1,1010 @@
+
+extern crate crypto;
+extern crate libflate;
+extern crate ndarray;
+extern crate reqwest;
+extern crate tokio;
+
+pub mod archive;
+pub mod cache_root;
+pub mod checksum;
+pub mod lock;
+pub mod manifest;
+pub mod progress;
+pub mod transport;
+
+use super::super::utils::natural_transform::opt_to_failure;
+use bytes::Bytes;
+use failure::Error;
+use futures_util::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{self, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub use self::archive::ArchiveKind;
+pub use self::cache_root::CacheRoot;
+pub use self::checksum::HashAlgorithm;
+pub use self::lock::CacheLock;
+pub use self::manifest::Manifest;
+pub use self::progress::{Progress, SilentProgress, TerminalProgress};
+pub use self::transport::{FileTransport, HttpTransport, MemoryTransport, Transport};
+
+/// The suffix of the marker written next to an archive once it has been extracted.
+/// The marker lists the extracted files relative to the directory.
+pub const EXTRACTED_SUFFIX: &str = ".extracted";
+
+fn remove_ext(fname: &str) -> Result<PathBuf, Error> {
+    Ok(PathBuf::from(opt_to_failure(
+        opt_to_failure(
+            PathBuf::from(fname).file_stem(),
+            "Cannot dedude the name of unarchived file from archived file",
+        )?
+        .to_str(),
+        "Cannot convert native string",
+    )?))
+}
+
+/// Information about the file to get. Used when building `FConf`
+#[derive(Debug, Clone, Copy)]
+pub struct RemoteFile<'a> {
+    /// Host name and its path
+    pub host_and_path: &'a str,
+    /// File name
+    pub fname: &'a str,
+    /// Digest of the file as a hex string
+    pub digest: &'a str,
+    /// The hash algorithm of `digest`
+    pub algorithm: HashAlgorithm,
+    /// Query used to get the file
+    pub query: &'a str,
+    /// The kind of archive. If it is specified, the file is extracted after download.
+    pub archive: Option<ArchiveKind>,
+}
+
+impl<'a> fmt::Display for RemoteFile<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "host_and_path: {}\n file name: {}\n {} hash: {}\n reqest query: {}",
+            self.host_and_path, self.fname, self.algorithm, self.digest, self.query
+        )
+    }
+}
+
+impl<'a> RemoteFile<'a> {
+    /// `RemoteFile` constructor. `digest` is regarded as SHA-256 (See `with_algorithm`).
+    pub fn new(host_and_path: &'a str, fname: &'a str, digest: &'a str, query: &'a str) -> Self {
+        Self {
+            host_and_path: host_and_path,
+            fname: fname,
+            digest: digest,
+            algorithm: HashAlgorithm::Sha256,
+            query: query,
+            archive: None,
+        }
+    }
+
+    /// `with_algorithm` specifies the hash algorithm of the digest
+    pub fn with_algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// `with_archive` specifies the kind of archive to be extracted after download
+    pub fn with_archive(mut self, kind: ArchiveKind) -> Self {
+        self.archive = Some(kind);
+        self
+    }
+}
+
+/// The owned `RemoteFile`. It can be built at runtime, e.g. from `Manifest`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoteFileBuf {
+    /// Host name and its path
+    pub host_and_path: String,
+    /// File name
+    pub fname: String,
+    /// Digest of the file as a hex string (`sha256` is also accepted as the key)
+    #[serde(alias = "sha256")]
+    pub digest: String,
+    /// The hash algorithm of `digest` (md5, sha1, sha256, sha512 or blake2b). Defaults to sha256.
+    #[serde(default)]
+    pub algorithm: HashAlgorithm,
+    /// Query used to get the file
+    #[serde(default)]
+    pub query: String,
+    /// The kind of archive. If it is specified, the file is extracted after download.
+    #[serde(default)]
+    pub archive: Option<ArchiveKind>,
+}
+
+impl fmt::Display for RemoteFileBuf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_remote_file().fmt(f)
+    }
+}
+
+impl RemoteFileBuf {
+    /// `RemoteFileBuf` constructor. `digest` is regarded as SHA-256.
+    pub fn new<S: Into<String>>(host_and_path: S, fname: S, digest: S, query: S) -> Self {
+        Self {
+            host_and_path: host_and_path.into(),
+            fname: fname.into(),
+            digest: digest.into(),
+            algorithm: HashAlgorithm::Sha256,
+            query: query.into(),
+            archive: None,
+        }
+    }
+
+    /// `as_remote_file` borrows `RemoteFileBuf` as `RemoteFile`
+    pub fn as_remote_file(&self) -> RemoteFile<'_> {
+        RemoteFile {
+            host_and_path: &self.host_and_path,
+            fname: &self.fname,
+            digest: &self.digest,
+            algorithm: self.algorithm,
+            query: &self.query,
+            archive: self.archive,
+        }
+    }
+}
+
+impl<'a> From<&RemoteFile<'a>> for RemoteFileBuf {
+    fn from(rf: &RemoteFile<'a>) -> Self {
+        Self {
+            host_and_path: rf.host_and_path.to_string(),
+            fname: rf.fname.to_string(),
+            digest: rf.digest.to_string(),
+            algorithm: rf.algorithm,
+            query: rf.query.to_string(),
+            archive: rf.archive,
+        }
+    }
+}
+
+/// The structure to perform initialization setting of `FetchClient`
+pub struct FConf<'a, T>
+where
+    T: Iterator<Item = &'a RemoteFile<'a>> + ExactSizeIterator,
+{
+    /// Directory name
+    pub save_dir_name: &'a str,
+    /// Information of the file to be fetched
+    pub remote_file: T,
+}
+
+impl<'a, T> FConf<'a, T>
+where
+    T: Iterator<Item = &'a RemoteFile<'a>> + ExactSizeIterator,
+{
+    /// `FConf` constructor
+    pub fn new(save_dir_name: &'a str, rf: T) -> Self {
+        Self {
+            save_dir_name: save_dir_name,
+            remote_file: rf,
+        }
+    }
+}
+
+/// Settings of `FetchClient` given by the caller
+#[derive(Clone)]
+pub struct ClientOptions {
+    /// Observer that is notified of the download, hash check and decoding progress
+    pub progress: Arc<dyn Progress>,
+    /// The directory under which the dataset directory is created
+    pub cache_root: CacheRoot,
+    /// The transport used to get the remote files
+    pub transport: Arc<dyn Transport>,
+    /// The time to wait for the lock of the directory held by another process
+    pub lock_timeout: Duration,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            progress: Arc::new(TerminalProgress::default()),
+            cache_root: CacheRoot::default(),
+            transport: Arc::new(HttpTransport),
+            lock_timeout: lock::DEFAULT_LOCK_TIMEOUT,
+        }
+    }
+}
+
+impl ClientOptions {
+    /// `ClientOptions` constructor. It reports the progress to the terminal.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `progress` sets the observer of the progress
+    pub fn progress(mut self, progress: Arc<dyn Progress>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// `silent` makes the client report nothing
+    pub fn silent(self) -> Self {
+        self.progress(Arc::new(SilentProgress))
+    }
+
+    /// `cache_root` sets the directory under which the dataset directory is created
+    pub fn cache_root(mut self, cache_root: CacheRoot) -> Self {
+        self.cache_root = cache_root;
+        self
+    }
+
+    /// `transport` sets the transport used to get the remote files
+    pub fn transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// `lock_timeout` sets the time to wait for the lock of the directory held by another process
+    pub fn lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+}
+
+/// The `FileInfo`mation
+#[derive(Clone)]
+pub struct FileInfo<'a> {
+    /// Host name and its path
+    pub host_and_path: Cow<'a, str>,
+    /// Digest of the file as a hex string
+    pub digest: Cow<'a, str>,
+    /// The hash algorithm of `digest`
+    pub algorithm: HashAlgorithm,
+    /// query used to get the file
+    pub query: Cow<'a, str>,
+    /// The kind of archive to be extracted after download
+    pub archive: Option<ArchiveKind>,
+}
+
+/// A type that executes the specified file existence check and creation,
+/// path resolution, etc.
+/// based on the save destination directory.
+#[derive(Clone)]
+pub struct DirClient<'a> {
+    save_dir: PathBuf,
+    /// Hash map with file name as key and related information as value
+    pub file: HashMap<String, FileInfo<'a>>,
+}
+
+impl<'a> DirClient<'a> {
+    /// `DirClient` constructor.
+    /// The directory is created under the root resolved by `CacheRoot::Auto`.
+    pub fn new<T>(cfg: FConf<'a, T>) -> io::Result<Self>
+    where
+        T: Iterator<Item = &'a RemoteFile<'a>> + ExactSizeIterator,
+    {
+        Self::with_root(cfg, &CacheRoot::Auto)
+    }
+
+    /// `with_root` constructs `DirClient` whose directory is created under `root`
+    pub fn with_root<T>(cfg: FConf<'a, T>, root: &CacheRoot) -> io::Result<Self>
+    where
+        T: Iterator<Item = &'a RemoteFile<'a>> + ExactSizeIterator,
+    {
+        let save_dir = root.dataset_dir(cfg.save_dir_name)?;
+        let mut hash = HashMap::new();
+        for elem in cfg.remote_file {
+            hash.insert(
+                elem.fname.to_string(),
+                FileInfo {
+                    host_and_path: Cow::Borrowed(elem.host_and_path),
+                    digest: Cow::Borrowed(elem.digest),
+                    algorithm: elem.algorithm,
+                    query: Cow::Borrowed(elem.query),
+                    archive: elem.archive,
+                },
+            );
+        }
+        Ok(Self {
+            save_dir,
+            file: hash,
+        })
+    }
+
+    /// `from_owned` constructs `DirClient` from the owned `RemoteFileBuf`s
+    ///
+    /// # Arguments
+    ///
+    /// * `save_dir_name` - Directory name
+    /// * `files` - Information of the files to be fetched
+    /// * `root` - The directory under which the directory is created
+    pub fn from_owned<I>(save_dir_name: &str, files: I, root: &CacheRoot) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = RemoteFileBuf>,
+    {
+        Ok(Self {
+            save_dir: root.dataset_dir(save_dir_name)?,
+            file: files
+                .into_iter()
+                .map(|elem| {
+                    (
+                        elem.fname,
+                        FileInfo {
+                            host_and_path: Cow::Owned(elem.host_and_path),
+                            digest: Cow::Owned(elem.digest),
+                            algorithm: elem.algorithm,
+                            query: Cow::Owned(elem.query),
+                            archive: elem.archive,
+                        },
+                    )
+                })
+                .collect(),
+        })
+    }
+
+    /// `path` returns the path of the directory
+    pub fn path(&self) -> &path::Path {
+        self.save_dir.as_path()
+    }
+
+    /// `file_path` creates the path of file x under
+    /// the directory from the specified file
+    /// name x
+    pub fn file_path(&self, fname: &str) -> PathBuf {
+        self.save_dir.join(PathBuf::from(fname))
+    }
+
+    /// `create` creates directory and its missing parents
+    pub fn create(&self) -> io::Result<()> {
+        if !self.exists() {
+            return fs::create_dir_all(self.path());
+        }
+        Ok(())
+    }
+
+    /// `file_create` creates file in directory.
+    /// The content is written to a temporary file first and then renamed,
+    /// so that an interrupted write never leaves a truncated file.
+    pub fn file_create(&self, dst: &str, src: &mut Bytes) -> io::Result<()> {
+        let tmp = self.file_path(&(dst.to_string() + ".part"));
+        {
+            let mut out = File::create(&tmp)?;
+            out.write_all(src)?;
+        }
+        fs::rename(tmp, self.file_path(dst))
+    }
+
+    /// `lock` creates the directory and takes its advisory lock,
+    /// waiting up to `timeout` while another process holds it.
+    /// The lock is released when the returned `CacheLock` is dropped.
+    pub fn lock(&self, timeout: Duration) -> io::Result<CacheLock> {
+        self.create()?;
+        CacheLock::acquire(&self.file_path(lock::LOCK_FILE_NAME), timeout)
+    }
+
+    /// `lock_async` is the same as `lock`
+    /// except that it waits for the lock without blocking the current thread.
+    pub async fn lock_async(&self, timeout: Duration) -> io::Result<CacheLock> {
+        self.create()?;
+        CacheLock::acquire_async(&self.file_path(lock::LOCK_FILE_NAME), timeout).await
+    }
+
+    /// `exists` checks if the specified directory exists
+    pub fn exists(&self) -> bool {
+        self.save_dir.exists()
+    }
+
+    /// `file_exists` checks if the specified file exists under the directory
+    pub fn file_exists(&self, fname: &str) -> bool {
+        self.file_path(fname).exists()
+    }
+
+    /// `extract` unpacks the archive `fname` under the directory.
+    /// The kind of archive is detected from its extension or magic number.
+    /// It returns the paths of the extracted files.
+    pub fn extract(&self, fname: &str) -> io::Result<Vec<PathBuf>> {
+        archive::extract(&self.file_path(fname), self.path())
+    }
+
+    /// `is_extracted` checks if the archive `fname` has been extracted
+    /// and all the extracted files still exist
+    pub fn is_extracted(&self, fname: &str) -> bool {
+        match fs::read_to_string(self.file_path(&(fname.to_string() + EXTRACTED_SUFFIX))) {
+            Ok(list) => list.lines().all(|p| self.file_exists(p)),
+            Err(_) => false,
+        }
+    }
+
+    fn mark_extracted(&self, fname: &str, extracted: &[PathBuf]) -> io::Result<()> {
+        let list = extracted
+            .iter()
+            .map(|p| p.strip_prefix(self.path()).unwrap_or(p).to_string_lossy() + "\n")
+            .collect::<String>();
+        self.file_create(
+            &(fname.to_string() + EXTRACTED_SUFFIX),
+            &mut Bytes::from(list),
+        )
+    }
+
+    /// Delete the specified file under the directory
+    pub fn rm_file(&self, fname: &str) -> Result<(), Error> {
+        if self.exists() && self.file_exists(fname) {
+            if let Err(e) = fs::remove_file(self.file_path(fname)) {
+                Err(failure::format_err!("{}", e.to_string()))
+            } else {
+                Ok(())
+            }
+        } else {
+            Err(failure::format_err!("no such file"))
+        }
+    }
+
+    /// `list` returns the files saved under the directory recursively,
+    /// sorted by their paths.
+    /// The lock file, the extraction markers and the temporary files of interrupted downloads
+    /// are not listed.
+    pub fn list(&self) -> io::Result<Vec<CachedFile>> {
+        let mut files = Vec::new();
+        if self.exists() {
+            list_files(self.path(), self.path(), &mut files)?;
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(files)
+    }
+
+    /// `purge` removes everything under the directory while holding its lock,
+    /// waiting up to `timeout` while another process holds it.
+    /// The directory and the lock file are kept, so that the processes waiting for the lock
+    /// remain excluded from each other. Nothing is done if the directory does not exist.
+    pub fn purge(&self, timeout: Duration) -> io::Result<()> {
+        if !self.exists() {
+            return Ok(());
+        }
+        let _lock = self.lock(timeout)?;
+        for entry in fs::read_dir(self.path())? {
+            let entry = entry?;
+            if entry.file_name() == lock::LOCK_FILE_NAME {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                fs::remove_dir_all(entry.path())?;
+            } else {
+                fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The file saved under the directory of `DirClient`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedFile {
+    /// The path relative to the directory
+    pub path: PathBuf,
+    /// The size of the file in bytes
+    pub size: u64,
+}
+
+fn list_files(base: &path::Path, dir: &path::Path, out: &mut Vec<CachedFile>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let meta = entry.metadata()?;
+        if meta.is_dir() {
+            list_files(base, &path, out)?;
+            continue;
+        }
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name == lock::LOCK_FILE_NAME
+            || name.ends_with(".part")
+            || name.ends_with(EXTRACTED_SUFFIX)
+        {
+            continue;
+        }
+        out.push(CachedFile {
+            path: path.strip_prefix(base).unwrap_or(&path).to_path_buf(),
+            size: meta.len(),
+        });
+    }
+    Ok(())
+}
+
+/// The state of the file specified in `FetchClient`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileStatus {
+    /// The file has not been downloaded yet
+    Missing,
+    /// The digest of the file matches
+    Verified,
+    /// The digest of the file does not match.
+    /// It has the message that names the expected and actual digests.
+    Corrupted(String),
+}
+
+impl fmt::Display for FileStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileStatus::Missing => write!(f, "missing"),
+            FileStatus::Verified => write!(f, "ok"),
+            FileStatus::Corrupted(_) => write!(f, "corrupted"),
+        }
+    }
+}
+
+/// The downloader of file
+#[derive(Clone)]
+pub struct FetchClient<'a> {
+    /// directory client
+    pub dir_client: DirClient<'a>,
+    progress: Arc<dyn Progress>,
+    transport: Arc<dyn Transport>,
+    lock_timeout: Duration,
+}
+
+impl<'a> FetchClient<'a> {
+    /// `FetchClient` constructor
+    pub fn new<T>(cfg: FConf<'a, T>) -> io::Result<Self>
+    where
+        T: Iterator<Item = &'a RemoteFile<'a>> + ExactSizeIterator,
+    {
+        Self::with_options(cfg, &ClientOptions::default())
+    }
+
+    /// `with_options` constructs `FetchClient` with the specified `ClientOptions`
+    pub fn with_options<T>(cfg: FConf<'a, T>, opts: &ClientOptions) -> io::Result<Self>
+    where
+        T: Iterator<Item = &'a RemoteFile<'a>> + ExactSizeIterator,
+    {
+        Ok(Self {
+            dir_client: DirClient::with_root(cfg, &opts.cache_root)?,
+            progress: opts.progress.clone(),
+            transport: opts.transport.clone(),
+            lock_timeout: opts.lock_timeout,
+        })
+    }
+
+    /// `from_owned` constructs `FetchClient` from the owned `RemoteFileBuf`s
+    ///
+    /// # Arguments
+    ///
+    /// * `save_dir_name` - Directory name
+    /// * `files` - Information of the files to be fetched
+    /// * `opts` - Settings of `FetchClient`
+    pub fn from_owned<I>(save_dir_name: &str, files: I, opts: &ClientOptions) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = RemoteFileBuf>,
+    {
+        Ok(Self {
+            dir_client: DirClient::from_owned(save_dir_name, files, &opts.cache_root)?,
+            progress: opts.progress.clone(),
+            transport: opts.transport.clone(),
+            lock_timeout: opts.lock_timeout,
+        })
+    }
+
+    /// `from_manifest` constructs `FetchClient` for the dataset described by `manifest`
+    pub fn from_manifest(manifest: &Manifest, opts: &ClientOptions) -> io::Result<Self> {
+        Self::from_owned(&manifest.save_dir, manifest.files.iter().cloned(), opts)
+    }
+
+    /// `progress` returns the observer of the progress
+    pub fn progress(&self) -> &dyn Progress {
+        self.progress.as_ref()
+    }
+
+    /// `lock_timeout` returns the time to wait for the lock of the directory
+    pub fn lock_timeout(&self) -> Duration {
+        self.lock_timeout
+    }
+
+    // The client which owns its settings, so that it can be moved to the blocking threads
+    fn to_static(&self) -> FetchClient<'static> {
+        FetchClient {
+            dir_client: DirClient {
+                save_dir: self.dir_client.save_dir.clone(),
+                file: self
+                    .dir_client
+                    .file
+                    .iter()
+                    .map(|(fname, elem)| {
+                        (
+                            fname.clone(),
+                            FileInfo {
+                                host_and_path: Cow::Owned(elem.host_and_path.to_string()),
+                                digest: Cow::Owned(elem.digest.to_string()),
+                                algorithm: elem.algorithm,
+                                query: Cow::Owned(elem.query.to_string()),
+                                archive: elem.archive,
+                            },
+                        )
+                    })
+                    .collect(),
+            },
+            progress: self.progress.clone(),
+            transport: self.transport.clone(),
+            lock_timeout: self.lock_timeout,
+        }
+    }
+
+    fn is_exists(&self) -> io::Result<bool> {
+        Ok(self.dir_client.exists() && self.dir_client.file.keys().all(|val| self.is_saved(val)))
+    }
+
+    /// `is_saved` checks if the file `fname` (or the file unarchived from it) exists
+    /// and, if it is an archive to be extracted, its extraction has completed.
+    fn is_saved(&self, fname: &str) -> bool {
+        let saved = self.dir_client.file_exists(fname)
+            || remove_ext(fname)
+                .ok()
+                .and_then(|s| s.to_str().map(|s| self.dir_client.file_exists(s)))
+                .unwrap_or(false);
+        let archive = self
+            .dir_client
+            .file
+            .get(fname)
+            .and_then(|elem| elem.archive);
+        saved && (archive.is_none() || self.dir_client.is_extracted(fname))
+    }
+
+    async fn fetch(&self, fname: &str) -> io::Result<Option<bytes::Bytes>> {
+        if let Some(elem) = self.dir_client.file.get(fname) {
+            let q = if elem.query.len() == 0 { "" } else { "?" };
+            let url = elem.host_and_path.to_string() + fname + q + &elem.query;
+            Ok(Some(
+                self.transport
+                    .fetch(fname, &url, self.progress.as_ref())
+                    .await?,
+            ))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn check_hash(&self, fname: &str, buf: &bytes::Bytes) -> io::Result<()> {
+        self.progress.hash_start(fname);
+
+        if let Some(elem) = self.dir_client.file.get(fname) {
+            let res = elem.algorithm.verify(fname, buf.as_ref(), &elem.digest);
+            self.progress.hash_finish(fname, res.is_ok());
+            return res;
+        }
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "the specified file is invalid",
+        ))
+    }
+
+    fn extract(&self, fname: &str) -> io::Result<()> {
+        if let Some(kind) = self
+            .dir_client
+            .file
+            .get(fname)
+            .and_then(|elem| elem.archive)
+        {
+            self.progress.extract_start(fname);
+            let extracted = archive::extract_as(
+                &self.dir_client.file_path(fname),
+                self.dir_client.path(),
+                kind,
+            )?;
+            self.dir_client.mark_extracted(fname, &extracted)?;
+            self.progress.extract_finish(fname);
+        }
+        Ok(())
+    }
+
+    /// `status` checks the digest of the saved file `fname`
+    pub fn status(&self, fname: &str) -> io::Result<FileStatus> {
+        let elem = self.dir_client.file.get(fname).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is not specified in this client", fname),
+            )
+        })?;
+        if !self.dir_client.file_exists(fname) {
+            return Ok(FileStatus::Missing);
+        }
+        let buf = fs::read(self.dir_client.file_path(fname))?;
+        Ok(match elem.algorithm.verify(fname, &buf, &elem.digest) {
+            Ok(()) => FileStatus::Verified,
+            Err(e) => FileStatus::Corrupted(e.to_string()),
+        })
+    }
+
+    /// `verify` checks the digests of all the specified files.
+    /// It returns the file names and their states sorted by the names.
+    pub fn verify(&self) -> io::Result<Vec<(String, FileStatus)>> {
+        let mut names = self.dir_client.file.keys().collect::<Vec<_>>();
+        names.sort();
+        names
+            .into_iter()
+            .map(|fname| Ok((fname.clone(), self.status(fname)?)))
+            .collect()
+    }
+
+    /// `get` downloads and save files based on settings.
+    /// The files whose kind of archive is specified are extracted after download.
+    /// The archive which has been downloaded but not extracted completely
+    /// (e.g. the extraction was interrupted) is extracted again without downloading it.
+    /// The directory is locked during the download and extraction,
+    /// so the processes sharing the directory download the files only once.
+    ///
+    /// It runs `get_async` on its own runtime, so it must not be called from an async context.
+    pub fn get(&self) -> io::Result<()> {
+        block_on(self.get_async())
+    }
+
+    /// `get_async` is the same as `get` except that it runs on the runtime of the caller.
+    /// It waits for the lock of the directory on the timer of the runtime,
+    /// and the hash check, the write and the extraction run on the blocking threads of the runtime.
+    pub async fn get_async(&self) -> io::Result<()> {
+        let _lock = match self.dir_client.lock_async(self.lock_timeout).await {
+            Ok(lock) => Some(lock),
+            // The read-only directory which already has all files can be used without the lock.
+            Err(ref e) if e.kind() == io::ErrorKind::PermissionDenied && self.is_exists()? => None,
+            Err(e) => return Err(e),
+        };
+
+        if self.is_exists()? {
+            self.progress.already_saved();
+            return Ok(());
+        }
+
+        self.progress.setup_start();
+
+        let owned = Arc::new(self.to_static());
+        stream::iter(self.dir_client.file.keys())
+            .fold(Ok(()), |acc, kf| {
+                let client = owned.clone();
+                let fname = kf.clone();
+                async move {
+                    if let Err(_) = acc {
+                        acc
+                    } else if self.is_saved(kf) {
+                        Ok(())
+                    } else if self.dir_client.file_exists(kf) {
+                        // The archive was saved after its hash check but has not been extracted.
+                        tokio::task::spawn_blocking(move || client.extract(&fname)).await?
+                    } else {
+                        match self.fetch(kf).await {
+                            Err(e) => Err(e),
+                            Ok(Some(mut s)) => {
+                                tokio::task::spawn_blocking(move || {
+                                    client.check_hash(&fname, &s)?;
+                                    client.dir_client.file_create(&fname, &mut s)?;
+                                    client.extract(&fname)
+                                })
+                                .await?
+                            }
+                            Ok(None) => Err(io::Error::new(
+                                io::ErrorKind::Other,
+                                "the specified file is invalid",
+                            )),
+                        }
+                    }
+                }
+            })
+            .await
+    }
+}
+
+/// `block_on` runs `future` to completion on a new runtime.
+/// It is used by the blocking APIs wrapping their async variants,
+/// so it panics if it is called from an async context; use the async variants there.
+pub fn block_on<F, T>(future: F) -> io::Result<T>
+where
+    F: std::future::Future<Output = io::Result<T>>,
+{
+    tokio::runtime::Runtime::new()?.block_on(future)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sha256_str(data: &[u8]) -> String {
+        HashAlgorithm::Sha256.hex_digest(data)
+    }
+
+    fn memory_options(root: &path::Path, data: &'static [u8]) -> ClientOptions {
+        ClientOptions::new()
+            .silent()
+            .cache_root(CacheRoot::Dir(root.to_path_buf()))
+            .transport(Arc::new(MemoryTransport::new().insert("data.bin", data)))
+    }
+
+    #[test]
+    fn test_get_from_memory_transport() {
+        const DATA: &[u8] = b"deep learning playground";
+        let root = tempfile::tempdir().unwrap();
+        let digest = sha256_str(DATA);
+        let files = [RemoteFile::new("mem://fixtures/", "data.bin", &digest, "")];
+        let client = FetchClient::with_options(
+            FConf::new(".fixtures", files.iter()),
+            &memory_options(root.path(), DATA),
+        )
+        .unwrap();
+
+        client.get().unwrap();
+        assert_eq!(client.dir_client.path(), root.path().join("fixtures"));
+        assert_eq!(
+            fs::read(client.dir_client.file_path("data.bin")).unwrap(),
+            DATA
+        );
+    }
+
+    #[test]
+    fn test_get_async_inside_runtime() {
+        const DATA: &[u8] = b"async playground";
+        let root = tempfile::tempdir().unwrap();
+        let digest = sha256_str(DATA);
+        let files = [RemoteFile::new("mem://fixtures/", "data.bin", &digest, "")];
+        let client = FetchClient::with_options(
+            FConf::new(".fixtures", files.iter()),
+            &memory_options(root.path(), DATA),
+        )
+        .unwrap();
+
+        // The blocking `get` cannot be used here since it would start a nested runtime.
+        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async { client.get_async().await }).unwrap();
+        assert_eq!(
+            fs::read(client.dir_client.file_path("data.bin")).unwrap(),
+            DATA
+        );
+    }
+
+    #[test]
+    fn test_get_extracts_again_after_output_removed() {
+        let mut writer = zip::ZipWriter::new(io::Cursor::new(vec![]));
+        writer
+            .start_file("dir/data.bin", zip::write::FileOptions::default())
+            .unwrap();
+        writer.write_all(b"extracted playground").unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+
+        let root = tempfile::tempdir().unwrap();
+        let digest = sha256_str(&archive);
+        let files = [RemoteFile::new("mem://fixtures/", "data.zip", &digest, "")
+            .with_archive(ArchiveKind::Zip)];
+        let client = FetchClient::with_options(
+            FConf::new("fixtures", files.iter()),
+            &ClientOptions::new()
+                .silent()
+                .cache_root(CacheRoot::Dir(root.path().to_path_buf()))
+                .transport(Arc::new(MemoryTransport::new().insert("data.zip", archive))),
+        )
+        .unwrap();
+
+        client.get().unwrap();
+        let extracted = client.dir_client.file_path("dir/data.bin");
+        assert!(client.dir_client.is_extracted("data.zip"));
+        assert_eq!(
+            client
+                .dir_client
+                .list()
+                .unwrap()
+                .into_iter()
+                .map(|f| f.path)
+                .collect::<Vec<_>>(),
+            vec![PathBuf::from("data.zip"), PathBuf::from("dir/data.bin")]
+        );
+
+        // The extraction interrupted halfway leaves the archive without its outputs.
+        fs::remove_file(&extracted).unwrap();
+        assert!(!client.dir_client.is_extracted("data.zip"));
+        client.get().unwrap();
+        assert_eq!(fs::read(&extracted).unwrap(), b"extracted playground");
+    }
+
+    #[test]
+    fn test_lock_waits_for_another_holder() {
+        let root = tempfile::tempdir().unwrap();
+        let files: [RemoteFile; 0] = [];
+        let dir_client = DirClient::with_root(
+            FConf::new("fixtures", files.iter()),
+            &CacheRoot::Dir(root.path().to_path_buf()),
+        )
+        .unwrap();
+
+        let lock = dir_client.lock(Duration::from_secs(1)).unwrap();
+        let err = dir_client.lock(Duration::from_millis(200)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        drop(lock);
+        assert!(dir_client.lock(Duration::from_millis(200)).is_ok());
+    }
+
+    #[test]
+    fn test_get_async_waits_for_lock_without_blocking() {
+        const DATA: &[u8] = b"async playground";
+        let root = tempfile::tempdir().unwrap();
+        let digest = sha256_str(DATA);
+        let files = [RemoteFile::new("mem://fixtures/", "data.bin", &digest, "")];
+        let client = FetchClient::with_options(
+            FConf::new(".fixtures", files.iter()),
+            &memory_options(root.path(), DATA).lock_timeout(Duration::from_secs(5)),
+        )
+        .unwrap();
+        let lock = client.dir_client.lock(Duration::from_secs(1)).unwrap();
+
+        // The task releasing the lock runs on the same thread as `get_async`,
+        // so it would time out if the waiting blocked the thread.
+        let mut rt = tokio::runtime::Builder::new()
+            .basic_scheduler()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let release = async {
+                tokio::time::delay_for(Duration::from_millis(300)).await;
+                drop(lock);
+                Ok(())
+            };
+            tokio::try_join!(client.get_async(), release)
+        })
+        .unwrap();
+        assert_eq!(
+            fs::read(client.dir_client.file_path("data.bin")).unwrap(),
+            DATA
+        );
+    }
+
+    #[test]
+    fn test_verify_and_list() {
+        const DATA: &[u8] = b"deep learning playground";
+        let root = tempfile::tempdir().unwrap();
+        let digest = sha256_str(DATA);
+        let files = [RemoteFile::new("mem://fixtures/", "data.bin", &digest, "")];
+        let client = FetchClient::with_options(
+            FConf::new("fixtures", files.iter()),
+            &memory_options(root.path(), DATA),
+        )
+        .unwrap();
+
+        assert_eq!(client.status("data.bin").unwrap(), FileStatus::Missing);
+        // Purging the dataset which has never been fetched does not create its directory.
+        client.dir_client.purge(Duration::from_secs(1)).unwrap();
+        assert!(!client.dir_client.exists());
+        client.get().unwrap();
+        assert_eq!(
+            client.verify().unwrap(),
+            vec![("data.bin".to_string(), FileStatus::Verified)]
+        );
+        assert_eq!(
+            client.dir_client.list().unwrap(),
+            vec![CachedFile {
+                path: PathBuf::from("data.bin"),
+                size: DATA.len() as u64,
+            }]
+        );
+
+        fs::write(client.dir_client.file_path("data.bin"), b"broken").unwrap();
+        match client.status("data.bin").unwrap() {
+            FileStatus::Corrupted(_) => (),
+            status => panic!("unexpected status: {:?}", status),
+        }
+
+        client.dir_client.purge(Duration::from_secs(1)).unwrap();
+        assert!(client.dir_client.list().unwrap().is_empty());
+        assert!(client.dir_client.file_exists(lock::LOCK_FILE_NAME));
+        assert_eq!(client.status("data.bin").unwrap(), FileStatus::Missing);
+    }
+
+    #[test]
+    fn test_get_rejects_hash_mismatch() {
+        let root = tempfile::tempdir().unwrap();
+        let digest = sha256_str(b"another content");
+        let files = [RemoteFile::new("mem://fixtures/", "data.bin", &digest, "")];
+        let client = FetchClient::with_options(
+            FConf::new("fixtures", files.iter()),
+            &memory_options(root.path(), b"deep learning playground"),
+        )
+        .unwrap();
+
+        assert!(client.get().is_err());
+        assert!(!client.dir_client.file_exists("data.bin"));
+    }
+}
@@ -0,0 +1,162 @@
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+/// `Progress` observes the stages of fetching and decoding a dataset.
+/// Every method has an empty default implementation,
+/// so an observer only needs to implement the stages it is interested in.
+pub trait Progress: Send + Sync {
+    /// Called when all of the specified files are already saved.
+    fn already_saved(&self) {}
+
+    /// Called once before the first file is downloaded.
+    fn setup_start(&self) {}
+
+    /// Called when the download of `fname` from `url` starts.
+    /// `total` is the size of the file in bytes if the server reported it.
+    fn download_start(&self, _fname: &str, _url: &str, _total: Option<u64>) {}
+
+    /// Called each time a chunk of `fname` has been received.
+    fn download_progress(&self, _fname: &str, _downloaded: u64, _total: Option<u64>) {}
+
+    /// Called when the download of `fname` has finished.
+    fn download_finish(&self, _fname: &str) {}
+
+    /// Called when the hash check of `fname` starts.
+    fn hash_start(&self, _fname: &str) {}
+
+    /// Called when the hash check of `fname` has finished.
+    fn hash_finish(&self, _fname: &str, _matched: bool) {}
+
+    /// Called when the extraction of the archive `fname` starts.
+    fn extract_start(&self, _fname: &str) {}
+
+    /// Called when the extraction of the archive `fname` has finished.
+    fn extract_finish(&self, _fname: &str) {}
+
+    /// Called when the decoding of `fname` starts.
+    fn decode_start(&self, _fname: &str) {}
+
+    /// Called when the decoding of `fname` has finished.
+    fn decode_finish(&self, _fname: &str) {}
+
+    /// Called when the decoded dataset could not be saved to the cache file `fname`.
+    /// The dataset is still loaded without the cache.
+    fn cache_failed(&self, _fname: &str, _error: &io::Error) {}
+}
+
+/// `SilentProgress` reports nothing.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SilentProgress;
+
+impl Progress for SilentProgress {}
+
+/// `TerminalProgress` reports the progress to the standard output,
+/// drawing a progress bar while a file is downloaded.
+#[derive(Debug)]
+pub struct TerminalProgress {
+    width: usize,
+    last_percent: Mutex<Option<u64>>,
+}
+
+impl Default for TerminalProgress {
+    fn default() -> Self {
+        Self::new(40)
+    }
+}
+
+impl TerminalProgress {
+    /// `TerminalProgress` constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The number of characters of the progress bar.
+    pub fn new(width: usize) -> Self {
+        Self {
+            width,
+            last_percent: Mutex::new(None),
+        }
+    }
+
+    fn draw(&self, fname: &str, downloaded: u64, total: Option<u64>) {
+        let mut out = io::stdout();
+        match total {
+            Some(total) if total > 0 => {
+                // A server may send more bytes than it announced.
+                let percent = (downloaded * 100 / total).min(100);
+                {
+                    let mut last = self.last_percent.lock().unwrap();
+                    if *last == Some(percent) {
+                        return;
+                    }
+                    *last = Some(percent);
+                }
+                let filled = self.width * percent as usize / 100;
+                let _ = write!(
+                    out,
+                    "\r{} [{}{}] {:>3}% ({}/{} bytes)",
+                    fname,
+                    "=".repeat(filled),
+                    " ".repeat(self.width - filled),
+                    percent,
+                    downloaded,
+                    total
+                );
+            }
+            _ => {
+                let _ = write!(out, "\r{} {} bytes", fname, downloaded);
+            }
+        }
+        let _ = out.flush();
+    }
+}
+
+impl Progress for TerminalProgress {
+    fn already_saved(&self) {
+        println!("the specified data is already saved.");
+    }
+
+    fn setup_start(&self) {
+        println!("Start to download and setup data (only first time execute)...");
+    }
+
+    fn download_start(&self, fname: &str, url: &str, _total: Option<u64>) {
+        *self.last_percent.lock().unwrap() = None;
+        println!("Fetching {} from {}", fname, url);
+    }
+
+    fn download_progress(&self, fname: &str, downloaded: u64, total: Option<u64>) {
+        self.draw(fname, downloaded, total);
+    }
+
+    fn download_finish(&self, _fname: &str) {
+        println!();
+    }
+
+    fn hash_start(&self, fname: &str) {
+        println!("Checking hash {}...", fname);
+    }
+
+    fn hash_finish(&self, fname: &str, matched: bool) {
+        if matched {
+            println!("hash value of file {} matched", fname);
+        } else {
+            println!("hash value of file {} does not match", fname);
+        }
+    }
+
+    fn extract_start(&self, fname: &str) {
+        println!("Extracting {}...", fname);
+    }
+
+    fn decode_start(&self, fname: &str) {
+        println!("start to decode {}...", fname);
+    }
+
+    fn decode_finish(&self, fname: &str) {
+        println!("Complete to decode {}", fname);
+    }
+
+    fn cache_failed(&self, fname: &str, error: &io::Error) {
+        eprintln!("failed to cache the decoded data to {}: {}", fname, error);
+    }
+}
@@ -0,0 +1,200 @@
+extern crate reqwest;
+extern crate tokio;
+
+use super::super::natural_transform::to_io;
+use super::progress::Progress;
+use bytes::{Bytes, BytesMut};
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::path::PathBuf;
+use std::pin::Pin;
+
+/// The future returned by `Transport::fetch`
+pub type TransportFuture<'a> = Pin<Box<dyn Future<Output = io::Result<Bytes>> + Send + 'a>>;
+
+/// `Transport` gets the content of the remote file for `FetchClient`.
+pub trait Transport: Send + Sync {
+    /// `fetch` gets the content of `fname` located at `url`
+    /// and reports the progress to `progress`.
+    ///
+    /// # Arguments
+    ///
+    /// * `fname` - File name
+    /// * `url` - URL built from `RemoteFile` (host and path, file name and query)
+    /// * `progress` - Observer of the progress
+    fn fetch<'a>(
+        &'a self,
+        fname: &'a str,
+        url: &'a str,
+        progress: &'a dyn Progress,
+    ) -> TransportFuture<'a>;
+}
+
+fn notify_whole(progress: &dyn Progress, fname: &str, url: &str, data: &Bytes) {
+    let total = data.len() as u64;
+    progress.download_start(fname, url, Some(total));
+    progress.download_progress(fname, total, Some(total));
+    progress.download_finish(fname);
+}
+
+// The error reported for the response of `status`, which is not successful
+fn status_error(url: &str, status: reqwest::StatusCode) -> io::Error {
+    let kind = if status == reqwest::StatusCode::NOT_FOUND {
+        io::ErrorKind::NotFound
+    } else {
+        io::ErrorKind::Other
+    };
+    io::Error::new(kind, format!("{} responded with {}", url, status))
+}
+
+/// `HttpTransport` gets the file over HTTP(S). This is the default transport.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HttpTransport;
+
+impl Transport for HttpTransport {
+    fn fetch<'a>(
+        &'a self,
+        fname: &'a str,
+        url: &'a str,
+        progress: &'a dyn Progress,
+    ) -> TransportFuture<'a> {
+        Box::pin(async move {
+            let res = to_io(reqwest::get(url).await, io::ErrorKind::Other)?;
+            // The error page of the server must not be saved as the file.
+            let status = res.status();
+            let mut res = res
+                .error_for_status()
+                .map_err(|_| status_error(url, status))?;
+            let total = res.content_length();
+            progress.download_start(fname, url, total);
+
+            let mut buf = BytesMut::new();
+            while let Some(chunk) = to_io(res.chunk().await, io::ErrorKind::Other)? {
+                buf.extend_from_slice(&chunk);
+                progress.download_progress(fname, buf.len() as u64, total);
+            }
+            progress.download_finish(fname);
+            Ok(buf.freeze())
+        })
+    }
+}
+
+/// `FileTransport` reads the file from the local filesystem.
+#[derive(Debug, Default, Clone)]
+pub struct FileTransport {
+    mirror: Option<PathBuf>,
+}
+
+impl FileTransport {
+    /// `FileTransport` constructor.
+    /// The URL is read as a `file://` URL or a path, ignoring the query.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `mirror` constructs `FileTransport` that reads the file of the same name under `dir`
+    /// regardless of the URL.
+    /// It is useful to serve the datasets from fixtures.
+    pub fn mirror<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            mirror: Some(dir.into()),
+        }
+    }
+
+    fn path(&self, fname: &str, url: &str) -> PathBuf {
+        match &self.mirror {
+            Some(dir) => dir.join(fname),
+            None => {
+                let path = url.split('?').next().unwrap_or(url);
+                PathBuf::from(path.trim_start_matches("file://"))
+            }
+        }
+    }
+}
+
+impl Transport for FileTransport {
+    fn fetch<'a>(
+        &'a self,
+        fname: &'a str,
+        url: &'a str,
+        progress: &'a dyn Progress,
+    ) -> TransportFuture<'a> {
+        let path = self.path(fname, url);
+        Box::pin(async move {
+            let data = Bytes::from(
+                tokio::fs::read(&path)
+                    .await
+                    .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?,
+            );
+            notify_whole(progress, fname, url, &data);
+            Ok(data)
+        })
+    }
+}
+
+/// `MemoryTransport` serves the file from the in-memory map.
+/// The entry is looked up by the URL first, and then by the file name.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryTransport {
+    files: HashMap<String, Bytes>,
+}
+
+impl MemoryTransport {
+    /// `MemoryTransport` constructor
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `insert` registers `data` as the content of `key` (URL or file name)
+    pub fn insert<K: Into<String>, B: Into<Bytes>>(mut self, key: K, data: B) -> Self {
+        self.files.insert(key.into(), data.into());
+        self
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn fetch<'a>(
+        &'a self,
+        fname: &'a str,
+        url: &'a str,
+        progress: &'a dyn Progress,
+    ) -> TransportFuture<'a> {
+        let found = self
+            .files
+            .get(url)
+            .or_else(|| self.files.get(fname))
+            .cloned();
+        Box::pin(async move {
+            let data = found.ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} is not registered in MemoryTransport", url),
+                )
+            })?;
+            notify_whole(progress, fname, url, &data);
+            Ok(data)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_status_error() {
+        let url = "https://example.com/data.bin";
+        let err = status_error(url, StatusCode::NOT_FOUND);
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert_eq!(
+            err.to_string(),
+            format!("{} responded with 404 Not Found", url)
+        );
+
+        let err = status_error(url, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+        assert!(err.to_string().contains("500"), "{}", err);
+    }
+}